@@ -2,9 +2,11 @@ use crossterm::style::Stylize;
 use std::collections::VecDeque;
 use std::io;
 
-use crate::hangul::{jamo::*, *};
+use crate::cycle::{Cycle, LoopDetector};
+use crate::hangul::*;
+use crate::sourcemap::{Pos, SourceMap};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct Aheui {
     pub cursor: (i32, i32),
     pub dir: (i32, i32),
@@ -16,14 +18,19 @@ pub struct Aheui {
     pub src_mat: (usize, usize),
     pub step: usize,
     pub ended: bool,
+    pub exit_code: Option<ExitCode>,
     use_debugger: bool,
     verbose: bool,
+    loop_detector: Option<LoopDetector>,
+    input: Option<String>,
+    source_map: Option<SourceMap>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExitCode {
     Success(i32),
     DivideByZero,
+    NonTerminating(Cycle),
 }
 
 impl Aheui {
@@ -62,6 +69,8 @@ impl Aheui {
         }
     }
 
+    // 명령마다 저장공간을 확인하는 원래 모양을 지킨다
+    #[allow(clippy::collapsible_match)]
     pub fn next(&mut self) {
         if self.ended {
             panic!("Cannot process after ended!");
@@ -94,7 +103,7 @@ impl Aheui {
                         let num1 = self.get_value(self.sel);
                         let num2 = self.get_value(self.sel);
 
-                        self.stacks[self.sel].push_front(num1 + num2);
+                        self.stacks[self.sel].push_front(num1.wrapping_add(num2));
 
                         valid = true;
                     }
@@ -105,7 +114,7 @@ impl Aheui {
                         let num1 = self.get_value(self.sel);
                         let num2 = self.get_value(self.sel);
 
-                        self.stacks[self.sel].push_front(num1 * num2);
+                        self.stacks[self.sel].push_front(num1.wrapping_mul(num2));
                         valid = true;
                     }
                 }
@@ -115,7 +124,7 @@ impl Aheui {
                         let num1 = self.get_value(self.sel);
                         let num2 = self.get_value(self.sel);
 
-                        self.stacks[self.sel].push_front(num2 - num1);
+                        self.stacks[self.sel].push_front(num2.wrapping_sub(num1));
                         valid = true;
                     }
                 }
@@ -125,12 +134,12 @@ impl Aheui {
                         let num1 = self.get_value(self.sel);
                         let num2 = self.get_value(self.sel);
 
-                        if num2 == 0 {
+                        if num1 == 0 {
                             self.exit(ExitCode::DivideByZero);
                             return;
                         }
 
-                        self.stacks[self.sel].push_front(num2 / num1);
+                        self.stacks[self.sel].push_front(num2.wrapping_div(num1));
                         valid = true;
                     }
                 }
@@ -140,12 +149,12 @@ impl Aheui {
                         let num1 = self.get_value(self.sel);
                         let num2 = self.get_value(self.sel);
 
-                        if num2 == 0 {
+                        if num1 == 0 {
                             self.exit(ExitCode::DivideByZero);
                             return;
                         }
 
-                        self.stacks[self.sel].push_front(num2 % num1);
+                        self.stacks[self.sel].push_front(num2.wrapping_rem(num1));
                         valid = true;
                     }
                 }
@@ -162,6 +171,7 @@ impl Aheui {
                                     self.output(num.to_string());
                                 }
                                 self.result.push_str(num.to_string().as_str());
+                                self.on_io();
                             }
                             ('ㅎ', ' ') => {
                                 let chr = char::from_u32(num as u32).unwrap_or(' ');
@@ -174,6 +184,7 @@ impl Aheui {
                                     self.output(chr.to_string());
                                 }
                                 self.result.push(chr);
+                                self.on_io();
                             }
                             _ => {}
                         }
@@ -197,7 +208,7 @@ impl Aheui {
                         ('ㅎ', ' ') => {
                             let mut line = String::new();
 
-                            self.request_input(RequestType::Number, &mut line);
+                            self.request_input(RequestType::Char, &mut line);
 
                             for chr in line.chars() {
                                 self.insert_value(self.sel, chr as i32);
@@ -273,32 +284,7 @@ impl Aheui {
             // 방향 재정의 예약 (ㅊ 등,)
 
             // 홀소리(모음) 실행 (방향)
-            self.dir = match curr.1 {
-                'ㅏ' => (1, 0),
-                'ㅓ' => (-1, 0),
-                'ㅗ' => (0, -1),
-                'ㅜ' => (0, 1),
-                'ㅑ' => (2, 0),
-                'ㅕ' => (-2, 0),
-                'ㅛ' => (0, -2),
-                'ㅠ' => (0, 2),
-                'ㅣ' => {
-                    if self.dir.1 == 0 {
-                        (-self.dir.0, 0)
-                    } else {
-                        self.dir
-                    }
-                }
-                'ㅡ' => {
-                    if self.dir.0 == 0 {
-                        (0, -self.dir.1)
-                    } else {
-                        self.dir
-                    }
-                }
-                'ㅢ' => (-self.dir.0, -self.dir.1),
-                _ => self.dir,
-            };
+            self.dir = turn(curr.1, self.dir);
 
             // 방향 재정의 실행
             if !valid {
@@ -312,20 +298,38 @@ impl Aheui {
 
         // 이동
         self.prev = self.cursor;
-        self.cursor.0 = (self.cursor.0 + self.dir.0).rem_euclid(self.src_mat.0 as i32);
-        self.cursor.1 = (self.cursor.1 + self.dir.1).rem_euclid(self.src_mat.1 as i32);
+        self.cursor = advance(self.cursor, self.dir, self.src_mat);
+
+        if let Some(detector) = &mut self.loop_detector
+            && let Some(cycle) =
+                detector.observe(self.step, self.cursor, self.dir, self.sel, &self.stacks)
+        {
+            self.exit(ExitCode::NonTerminating(cycle));
+        }
     }
 
-    fn check_require(&self, count: usize) -> bool {
-        self.stacks[self.sel].len() >= count
+    fn check_require(&mut self, count: usize) -> bool {
+        let ok = self.stacks[self.sel].len() >= count;
+
+        if let (false, Some(detector)) = (ok, &mut self.loop_detector) {
+            detector.on_check_failed();
+        }
+
+        ok
     }
 
     fn get_value(&mut self, sel: usize) -> i32 {
-        if sel == 21 {
+        let value = if sel == 21 {
             self.stacks[21].pop_front().unwrap_or(0)
         } else {
             self.stacks[sel].pop_front().unwrap_or(0)
+        };
+
+        if let Some(detector) = &mut self.loop_detector {
+            detector.on_pop(sel, self.stacks[sel].len());
         }
+
+        value
     }
 
     fn insert_value(&mut self, sel: usize, val: i32) {
@@ -336,6 +340,25 @@ impl Aheui {
         }
     }
 
+    /// 격자와 설정은 두고 실행 상태만 처음으로 되돌린다.
+    /// 입력은 읽은 만큼 줄어 있으므로 `set_input`으로 다시 준다
+    pub fn reset(&mut self) {
+        self.cursor = (0, 0);
+        self.dir = (0, 1);
+        self.prev = (0, 0);
+        for stack in self.stacks.iter_mut() {
+            stack.clear();
+        }
+        self.result.clear();
+        self.sel = 0;
+        self.step = 0;
+        self.ended = false;
+        self.exit_code = None;
+        if let Some(detector) = &mut self.loop_detector {
+            detector.reset();
+        }
+    }
+
     pub fn verbose(&mut self, opt: bool) {
         self.verbose = opt;
     }
@@ -344,22 +367,73 @@ impl Aheui {
         self.use_debugger = opt;
     }
 
+    /// 표준 입력 대신 읽을 입력
+    pub fn set_input(&mut self, input: String) {
+        self.input = Some(input);
+    }
+
+    /// `interval` 걸음마다 상태 반복을 검사. `None`이면 끔
+    pub fn detect_loops(&mut self, interval: Option<usize>) {
+        self.loop_detector = interval.map(LoopDetector::new);
+    }
+
+    /// 만들어 낸 프로그램이면 오류에 처음 입력의 자리도 적는다
+    pub fn set_source_map(&mut self, map: Option<SourceMap>) {
+        self.source_map = map;
+    }
+
+    /// 셀을 만든 입력의 자리
+    pub fn origin(&self, cell: (i32, i32)) -> Option<Pos> {
+        self.source_map.as_ref()?.get(cell)
+    }
+
+    /// ` (line 3, column 5)` 또는 빈 문자열
+    fn origin_note(&self, cell: (i32, i32)) -> String {
+        self.origin(cell)
+            .map(|(line, col)| format!(" (line {}, column {})", line, col))
+            .unwrap_or_default()
+    }
+
     pub fn exit(&mut self, code: ExitCode) {
+        self.ended = true;
+        self.exit_code = Some(code);
+
+        if self.use_debugger {
+            return;
+        }
+
         println!("\n");
 
         if self.verbose {
             print!("Final result: {}", self.result);
         }
 
-        self.ended = true;
         match &code {
             ExitCode::Success(_) if self.verbose => {
                 println!("Finished, {:?}", code);
             }
             ExitCode::DivideByZero => {
                 println!(
-                    "{:?}: divide by 0 at ({}, {})",
-                    code, self.cursor.0, self.cursor.1
+                    "{:?}: divide by 0 at ({}, {}){}",
+                    code,
+                    self.cursor.0,
+                    self.cursor.1,
+                    self.origin_note(self.cursor)
+                );
+            }
+            ExitCode::NonTerminating(cycle) => {
+                println!(
+                    "non-terminating: state at step {} repeats step {} at ({}, {}){}{}",
+                    cycle.step,
+                    cycle.repeats,
+                    cycle.cursor.0,
+                    cycle.cursor.1,
+                    self.origin_note(cycle.cursor),
+                    if cycle.growing {
+                        ", storages only grow"
+                    } else {
+                        ""
+                    }
                 );
             }
             _ => {}
@@ -390,8 +464,8 @@ impl Aheui {
         }
 
         for (idx, items) in self.stacks.iter().enumerate() {
-            if items.len() > 0 {
-                println!("{}: {:?}", assemble_jamo('ㅇ', 'ㅏ', _END[idx]), items);
+            if !items.is_empty() {
+                println!("{}: {:?}", storage_name(idx), items);
             }
         }
     }
@@ -408,15 +482,110 @@ impl Aheui {
         }
     }
 
-    fn request_input(&mut self, request_type: RequestType, line: &mut String) {
-        if self.use_debugger {
+    fn on_io(&mut self) {
+        if let Some(detector) = &mut self.loop_detector {
+            detector.on_io(self.step);
+        }
+    }
+
+    fn request_input(&mut self, _request_type: RequestType, line: &mut String) {
+        self.on_io();
+        if let Some(input) = &mut self.input {
+            // 한 줄씩 읽음
+            let end = input.find('\n').map_or(input.len(), |i| i + 1);
+            line.extend(input.drain(..end));
+        } else if self.use_debugger {
         } else {
             io::stdin().read_line(line).unwrap();
         }
     }
 }
 
+/// 홀소리(모음)에 따라 바뀐 방향
+pub fn turn(vowel: char, dir: (i32, i32)) -> (i32, i32) {
+    match vowel {
+        'ㅏ' => (1, 0),
+        'ㅓ' => (-1, 0),
+        'ㅗ' => (0, -1),
+        'ㅜ' => (0, 1),
+        'ㅑ' => (2, 0),
+        'ㅕ' => (-2, 0),
+        'ㅛ' => (0, -2),
+        'ㅠ' => (0, 2),
+        'ㅣ' => {
+            if dir.1 == 0 {
+                (-dir.0, 0)
+            } else {
+                dir
+            }
+        }
+        'ㅡ' => {
+            if dir.0 == 0 {
+                (0, -dir.1)
+            } else {
+                dir
+            }
+        }
+        'ㅢ' => (-dir.0, -dir.1),
+        _ => dir,
+    }
+}
+
+/// `src_mat` 크기로 감싸며 한 칸 이동
+pub fn advance(cursor: (i32, i32), dir: (i32, i32), src_mat: (usize, usize)) -> (i32, i32) {
+    (
+        (cursor.0 + dir.0).rem_euclid(src_mat.0 as i32),
+        (cursor.1 + dir.1).rem_euclid(src_mat.1 as i32),
+    )
+}
+
 enum RequestType {
     Number,
     Char,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(src: &str, input: &str) -> (String, Option<ExitCode>) {
+        let mut aheui = Aheui::new(src.to_string());
+        aheui.debug(true);
+        aheui.set_input(input.to_string());
+        while !aheui.ended && aheui.step < 100 {
+            aheui.next();
+        }
+        (aheui.result, aheui.exit_code)
+    }
+
+    #[test]
+    fn wraps_arithmetic_around_i32() {
+        let output = |src, input| run(src, input).0;
+        assert_eq!(output("방방다망희", "2147483647\n1\n"), "-2147483648");
+        assert_eq!(output("방방따망희", "65536\n65536\n"), "0");
+        assert_eq!(output("방방타망희", "-2147483648\n1\n"), "2147483647");
+        assert_eq!(output("방방나망희", "-2147483648\n-1\n"), "-2147483648");
+        assert_eq!(output("방방라망희", "-2147483648\n-1\n"), "0");
+    }
+
+    #[test]
+    fn only_a_zero_divisor_fails() {
+        for src in ["방방나망희", "방방라망희"] {
+            assert_eq!(
+                run(src, "5\n0\n"),
+                (String::new(), Some(ExitCode::DivideByZero))
+            );
+            assert_eq!(
+                run(src, "0\n5\n"),
+                ("0".to_string(), Some(ExitCode::Success(0)))
+            );
+        }
+    }
+
+    #[test]
+    fn reads_a_character_line_at_a_time() {
+        // 한 줄을 글자마다 넣으므로 줄바꿈이 맨 위
+        assert_eq!(run("밯망망희", "가\n").0, "1044032");
+        assert_eq!(run("밯망망밯망망희", "a\nb\n").0, "10971098");
+    }
+}
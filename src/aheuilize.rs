@@ -0,0 +1,189 @@
+use std::str::FromStr;
+
+use crate::constant::{Op, Synthesizer};
+use crate::hangul::{assemble_jamo, disassemble_jamo};
+use crate::literary::{self, literary};
+use crate::sourcemap::{Pos, SourceMap};
+use crate::transform::Transform;
+
+/// 문자로 출력
+const PRINT: Op = ('ㅁ', ('ㅎ', ' '));
+const END: Op = ('ㅎ', (' ', ' '));
+
+/// 만들어 낸 프로그램의 모양
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// 행마다 번갈아 오른쪽, 왼쪽으로
+    Snake,
+    /// 열마다 번갈아 아래, 위로
+    Columns,
+    /// `Snake`를 낱말로 채운 것
+    Literary,
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "snake" => Ok(Self::Snake),
+            "columns" => Ok(Self::Columns),
+            "literary" => Ok(Self::Literary),
+            _ => Err(format!(
+                "unknown style {:?}, expected snake, columns or literary",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    /// 한 행(`Columns`이면 한 열)의 최대 칸 수. 0이면 접지 않는다
+    pub width: usize,
+    /// 글의 줄이 바뀌면 프로그램도 다음 행으로 넘긴다
+    pub wrap_lines: bool,
+    pub style: Style,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            width: 40,
+            wrap_lines: false,
+            style: Style::Snake,
+        }
+    }
+}
+
+/// `text`를 그대로 출력하고 끝나는 프로그램
+pub fn aheuilize(text: &str, layout: Layout) -> String {
+    aheuilize_with_map(text, layout).0
+}
+
+/// `aheuilize`와 같고, 셀마다 그 셀이 출력하는 글자의 자리를 함께
+pub fn aheuilize_with_map(text: &str, layout: Layout) -> (String, SourceMap) {
+    // (명령, 다음 명령을 새 행에서 시작하는지, 출력할 글자의 자리)
+    let mut synth = Synthesizer::default();
+    let mut ops: Vec<(Op, bool, Option<Pos>)> = vec![];
+    let (mut line, mut col) = (1, 1);
+    for c in text.chars() {
+        let pos = Some((line, col));
+        ops.extend(
+            synth
+                .synthesize(c as i32)
+                .into_iter()
+                .map(|op| (op, false, pos)),
+        );
+        ops.push((PRINT, layout.wrap_lines && c == '\n', pos));
+        if c == '\n' {
+            (line, col) = (line + 1, 1);
+        } else {
+            col += 1;
+        }
+    }
+    ops.push((END, false, None));
+
+    // 행 끝에서 아래로 내려가 방향을 뒤집는다
+    let mut cells: Vec<((usize, usize), char, Option<Pos>)> = vec![];
+    let (mut x, mut y, mut dx) = (0usize, 0usize, 1isize);
+    for (i, &((start, end), wrap, pos)) in ops.iter().enumerate() {
+        let vowel = if i + 1 == ops.len() {
+            'ㅏ'
+        } else {
+            let next = x as isize + dx;
+            if wrap || next < 0 || (layout.width > 0 && next >= layout.width as isize) {
+                dx = -dx;
+                'ㅜ'
+            } else if dx > 0 {
+                'ㅏ'
+            } else {
+                'ㅓ'
+            }
+        };
+        cells.push(((x, y), assemble_jamo(start, vowel, end), pos));
+
+        if vowel == 'ㅜ' {
+            y += 1;
+        } else {
+            x = (x as isize + dx) as usize;
+        }
+    }
+
+    if layout.style == Style::Columns {
+        for ((x, y), chr, _) in cells.iter_mut() {
+            let k = disassemble_jamo(*chr);
+            (*x, *y) = (*y, *x);
+            *chr = assemble_jamo(k.0, Transform::Transpose.vowel(k.1), k.2);
+        }
+    }
+
+    let width = cells.iter().map(|((x, _), _, _)| x + 1).max().unwrap_or(0);
+    let height = cells.iter().map(|((_, y), _, _)| y + 1).max().unwrap_or(0);
+    let mut grid = vec![vec![' '; width]; height];
+    let mut map = SourceMap::default();
+    for ((x, y), chr, pos) in cells {
+        grid[y][x] = chr;
+        if let Some(pos) = pos {
+            map.insert((x as i32, y as i32), pos);
+        }
+    }
+
+    let src: String = grid
+        .iter()
+        .map(|row| row.iter().collect::<String>().trim_end().to_string() + "\n")
+        .collect();
+
+    let src = match layout.style {
+        Style::Literary => literary(&src, literary::WORDS, 0),
+        _ => src,
+    };
+    (src, map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aheui::{Aheui, ExitCode};
+    use crate::equiv::run;
+
+    const TEXTS: [&str; 4] = ["", "Hello, World!\n", "아희 😃\n둘째 줄\n", "0\n\n-1"];
+
+    #[test]
+    fn prints_the_text_in_every_layout() {
+        for text in TEXTS {
+            for style in [Style::Snake, Style::Columns, Style::Literary] {
+                for width in [0, 1, 3, 40] {
+                    for wrap_lines in [false, true] {
+                        let layout = Layout {
+                            width,
+                            wrap_lines,
+                            style,
+                        };
+                        let src = aheuilize(text, layout);
+                        let outcome = run(&Aheui::new(src.clone()), "", 1_000_000);
+                        assert_eq!(outcome.output, text, "{:?}\n{}", layout, src);
+                        assert_eq!(outcome.exit, Some(ExitCode::Success(0)), "{:?}", layout);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn maps_each_print_to_its_character() {
+        let text = "ab\nc";
+        let (src, map) = aheuilize_with_map(text, Layout::default());
+        let aheui = Aheui::new(src);
+        let mut prints = vec![];
+        for (y, row) in aheui.src_map.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if cell.0 == 'ㅁ' {
+                    prints.extend(map.get((x as i32, y as i32)));
+                }
+            }
+        }
+        prints.sort();
+        assert_eq!(prints, vec![(1, 1), (1, 2), (1, 3), (2, 1)]);
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::aheui::{Aheui, advance, turn};
+use crate::hangul::{KChar, get_end_count};
+
+/// 이 횟수만큼 합쳐진 뒤에도 구간이 넓어지면 끝을 무한으로 넓힌다
+const WIDEN_AFTER: usize = 8;
+
+/// 저장공간 크기의 구간. `hi`가 `None`이면 상한 없음
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Depth {
+    pub lo: usize,
+    pub hi: Option<usize>,
+}
+
+impl Depth {
+    pub const EMPTY: Depth = Depth { lo: 0, hi: Some(0) };
+
+    fn join(self, other: Depth) -> Depth {
+        Depth {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.zip(other.hi).map(|(a, b)| a.max(b)),
+        }
+    }
+
+    fn widen(self, next: Depth) -> Depth {
+        Depth {
+            lo: if next.lo < self.lo { 0 } else { self.lo },
+            hi: match (self.hi, next.hi) {
+                (Some(a), Some(b)) if b <= a => Some(a),
+                _ => None,
+            },
+        }
+    }
+
+    fn add(self, n: usize) -> Depth {
+        Depth {
+            lo: self.lo + n,
+            hi: self.hi.map(|h| h + n),
+        }
+    }
+
+    fn sub(self, n: usize) -> Depth {
+        Depth {
+            lo: self.lo.saturating_sub(n),
+            hi: self.hi.map(|h| h.saturating_sub(n)),
+        }
+    }
+
+    /// `check_require(count)`가 통과하는 부분과 실패하는 부분으로 나눈다
+    fn split(self, count: usize) -> (Option<Depth>, Option<Depth>) {
+        let pass = match self.hi {
+            Some(h) if h < count => None,
+            _ => Some(Depth {
+                lo: self.lo.max(count),
+                hi: self.hi,
+            }),
+        };
+        let fail = if self.lo < count {
+            Some(Depth {
+                lo: self.lo,
+                hi: Some(self.hi.map_or(count - 1, |h| h.min(count - 1))),
+            })
+        } else {
+            None
+        };
+
+        (pass, fail)
+    }
+
+    pub fn verdict(self, count: usize) -> Verdict {
+        match self.split(count) {
+            (Some(_), None) => Verdict::Pass,
+            (None, Some(_)) => Verdict::Fail,
+            _ => Verdict::Unknown,
+        }
+    }
+}
+
+pub type Depths = [Depth; 27];
+
+/// 실행 직전의 기계 상태 중 위치에 관한 부분
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct State {
+    pub cursor: (i32, i32),
+    pub dir: (i32, i32),
+    pub sel: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// `check_require`가 항상 통과
+    Pass,
+    /// `check_require`가 항상 실패해 반사됨
+    Fail,
+    Unknown,
+}
+
+impl Verdict {
+    fn join(self, other: Verdict) -> Verdict {
+        if self == other {
+            self
+        } else {
+            Verdict::Unknown
+        }
+    }
+}
+
+/// 셀을 실행하려면 선택된 저장공간에 필요한 값의 개수
+pub fn required_depth(chr: &KChar) -> Option<usize> {
+    match chr.0 {
+        'ㄷ' | 'ㄸ' | 'ㅌ' | 'ㄴ' | 'ㄹ' | 'ㅍ' | 'ㅈ' => Some(2),
+        'ㅁ' | 'ㅃ' | 'ㅆ' | 'ㅊ' => Some(1),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub cursor: (i32, i32),
+    pub chr: char,
+    pub sel: Vec<usize>,
+    pub verdict: Verdict,
+}
+
+/// 도달 가능한 모든 (셀, 방향, 선택) 상태에서 각 저장공간 크기의 상하한
+#[derive(Debug, Default)]
+pub struct DepthAnalysis {
+    pub states: HashMap<State, Depths>,
+}
+
+impl DepthAnalysis {
+    pub fn run(aheui: &Aheui) -> Self {
+        let mut analysis = Self::default();
+
+        if aheui.src_map.is_empty() || aheui.src_mat.0 == 0 {
+            return analysis;
+        }
+
+        let start = State {
+            cursor: (0, 0),
+            dir: (0, 1),
+            sel: 0,
+        };
+        let mut visits: HashMap<State, usize> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        let mut queued = HashSet::from([start]);
+        analysis.states.insert(start, [Depth::EMPTY; 27]);
+
+        while let Some(state) = queue.pop_front() {
+            queued.remove(&state);
+            let depths = analysis.states[&state];
+
+            for (next, next_depths) in transfer(aheui, state, depths) {
+                let count = visits.entry(next).or_insert(0);
+                let merged = match analysis.states.get(&next) {
+                    None => next_depths,
+                    Some(old) => {
+                        let mut merged = *old;
+                        for (m, (o, n)) in merged.iter_mut().zip(old.iter().zip(next_depths)) {
+                            *m = o.join(n);
+                            if *count >= WIDEN_AFTER {
+                                *m = o.widen(*m);
+                            }
+                        }
+                        if merged == *old {
+                            continue;
+                        }
+                        merged
+                    }
+                };
+
+                *count += 1;
+                analysis.states.insert(next, merged);
+                if queued.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        analysis
+    }
+
+    /// 도달 가능한 상태에서 다음 상태로의 모든 이동
+    pub fn edges(&self, aheui: &Aheui) -> Vec<(State, State)> {
+        self.states
+            .iter()
+            .flat_map(|(&state, &depths)| {
+                transfer(aheui, state, depths)
+                    .into_iter()
+                    .map(move |(next, _)| (state, next))
+            })
+            .collect()
+    }
+
+    /// 한 셀의 모든 도달 상태를 합친 판정
+    pub fn verdict_at(&self, aheui: &Aheui, cursor: (i32, i32)) -> Option<Verdict> {
+        let count = required_depth(&cell(aheui, cursor))?;
+
+        self.states
+            .iter()
+            .filter(|(s, _)| s.cursor == cursor)
+            .map(|(s, d)| d[s.sel].verdict(count))
+            .reduce(Verdict::join)
+    }
+
+    /// 항상 통과하거나 항상 반사되는 셀 목록
+    pub fn findings(&self, aheui: &Aheui) -> Vec<Finding> {
+        let mut cursors: Vec<(i32, i32)> = self.states.keys().map(|s| s.cursor).collect();
+        cursors.sort_by_key(|&(x, y)| (y, x));
+        cursors.dedup();
+
+        cursors
+            .into_iter()
+            .filter_map(|cursor| {
+                let verdict = self.verdict_at(aheui, cursor)?;
+                let mut sel: Vec<usize> = self
+                    .states
+                    .keys()
+                    .filter(|s| s.cursor == cursor)
+                    .map(|s| s.sel)
+                    .collect();
+                sel.sort();
+                sel.dedup();
+
+                Some(Finding {
+                    cursor,
+                    chr: cell(aheui, cursor).3,
+                    sel,
+                    verdict,
+                })
+            })
+            .collect()
+    }
+}
+
+fn cell(aheui: &Aheui, cursor: (i32, i32)) -> KChar {
+    aheui.src_map[cursor.1 as usize][cursor.0 as usize]
+}
+
+/// `Aheui::next`를 크기 구간 위에서 흉내낸다
+fn transfer(aheui: &Aheui, state: State, depths: Depths) -> Vec<(State, Depths)> {
+    let curr = cell(aheui, state.cursor);
+    let sel = state.sel;
+
+    // (통과 여부, 다음 선택, 크기)
+    let mut outcomes: Vec<(bool, usize, Depths)> = Vec::new();
+
+    if curr.0 == ' ' {
+        let next = State {
+            cursor: advance(state.cursor, state.dir, aheui.src_mat),
+            ..state
+        };
+        return vec![(next, depths)];
+    }
+
+    match curr.0 {
+        'ㅎ' => return Vec::new(),
+        // ㅎ 저장공간(통로)은 인터프리터에 없어 더 진행하지 못한다
+        'ㅅ' | 'ㅆ' if get_end_count(curr.2) >= depths.len() => return Vec::new(),
+        'ㅇ' | 'ㄱ' | 'ㄲ' => outcomes.push((true, sel, depths)),
+        'ㅅ' => outcomes.push((true, get_end_count(curr.2), depths)),
+        'ㅂ' => {
+            let mut d = depths;
+            d[sel] = match curr.2 {
+                // 한 줄의 모든 글자를 넣으므로 개수를 알 수 없음
+                ('ㅎ', ' ') => Depth {
+                    lo: d[sel].lo,
+                    hi: None,
+                },
+                _ => d[sel].add(1),
+            };
+            outcomes.push((true, sel, d));
+        }
+        op => {
+            if let Some(count) = required_depth(&curr) {
+                let (pass, fail) = depths[sel].split(count);
+
+                if let Some(pass) = pass {
+                    let mut d = depths;
+                    d[sel] = pass;
+                    match op {
+                        'ㅃ' => d[sel] = d[sel].add(1),
+                        'ㅍ' => {}
+                        'ㅆ' => {
+                            // 대기열(ㅇ)도 꺼내고 넣는 끝만 다를 뿐 크기 변화는 같다
+                            d[sel] = d[sel].sub(1);
+                            let to = get_end_count(curr.2);
+                            d[to] = d[to].add(1);
+                        }
+                        'ㅊ' => {
+                            d[sel] = d[sel].sub(1);
+                            // 꺼낸 값이 0이면 반사
+                            outcomes.push((false, sel, d));
+                        }
+                        _ => d[sel] = d[sel].sub(1),
+                    }
+                    outcomes.push((true, sel, d));
+                }
+                if let Some(fail) = fail {
+                    let mut d = depths;
+                    d[sel] = fail;
+                    outcomes.push((false, sel, d));
+                }
+            } else {
+                // ㅉ, ㅋ 등은 아무 동작 없이 반사
+                outcomes.push((false, sel, depths));
+            }
+        }
+    }
+
+    outcomes
+        .into_iter()
+        .map(|(valid, sel, d)| {
+            let mut dir = turn(curr.1, state.dir);
+            if !valid {
+                dir = (-dir.0, -dir.1);
+            }
+            let next = State {
+                cursor: advance(state.cursor, dir, aheui.src_mat),
+                dir,
+                sel,
+            };
+            (next, d)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn depth(lo: usize, hi: Option<usize>) -> Depth {
+        Depth { lo, hi }
+    }
+
+    /// 인터프리터로 실행하며 지난 상태마다 실제 크기가 구간 안에 있는지 본다
+    fn assert_sound(src: &str, steps: usize) -> DepthAnalysis {
+        let mut aheui = Aheui::new(src.to_string());
+        let analysis = DepthAnalysis::run(&aheui);
+        while !aheui.ended && aheui.step < steps {
+            let state = State {
+                cursor: aheui.cursor,
+                dir: aheui.dir,
+                sel: aheui.sel,
+            };
+            let depths = analysis.states.get(&state).expect("reached state");
+            for (idx, d) in depths.iter().enumerate() {
+                let len = aheui.stacks[idx].len();
+                assert!(
+                    d.lo <= len && d.hi.is_none_or(|h| len <= h),
+                    "{} has {} values at step {}, outside {:?}",
+                    idx,
+                    len,
+                    aheui.step,
+                    d
+                );
+            }
+            aheui.next();
+        }
+        analysis
+    }
+
+    #[test]
+    fn splits_on_the_required_count() {
+        assert_eq!(
+            depth(1, Some(3)).split(2),
+            (Some(depth(2, Some(3))), Some(depth(1, Some(1))))
+        );
+        assert_eq!(
+            depth(0, None).split(2),
+            (Some(depth(2, None)), Some(depth(0, Some(1))))
+        );
+        assert_eq!(Depth::EMPTY.split(1), (None, Some(Depth::EMPTY)));
+        assert_eq!(depth(2, None).split(2), (Some(depth(2, None)), None));
+
+        assert_eq!(depth(2, None).verdict(2), Verdict::Pass);
+        assert_eq!(depth(0, Some(1)).verdict(2), Verdict::Fail);
+        assert_eq!(depth(1, Some(2)).verdict(2), Verdict::Unknown);
+    }
+
+    #[test]
+    fn widens_a_loop_that_keeps_pushing() {
+        // 오른쪽으로 감싸며 끝없이 넣는다
+        let aheui = Aheui::new("바\n".to_string());
+        let analysis = DepthAnalysis::run(&aheui);
+        let state = State {
+            cursor: (0, 0),
+            dir: (1, 0),
+            sel: 0,
+        };
+        assert_eq!(analysis.states[&state][0], depth(1, None));
+    }
+
+    #[test]
+    fn judges_each_cell() {
+        let verdict = |src: &str, cursor| {
+            let aheui = Aheui::new(src.to_string());
+            DepthAnalysis::run(&aheui).verdict_at(&aheui, cursor)
+        };
+        assert_eq!(verdict("바마희", (1, 0)), Some(Verdict::Pass));
+        assert_eq!(verdict("마희", (0, 0)), Some(Verdict::Fail));
+        // 처음에는 비어서 반사되고, 감싸 돌아온 뒤에는 `바`가 넣은 값이 있다
+        assert_eq!(verdict("마바", (0, 0)), Some(Verdict::Unknown));
+        assert_eq!(verdict("바마희", (0, 0)), None);
+    }
+
+    #[test]
+    fn bounds_what_the_interpreter_does() {
+        assert_sound("반받다빠마마마희", 100);
+        assert_sound("밦밦빠\n희ㅇ처\nㅇㅇ바\n", 100);
+        assert_sound("마바빠파\n", 100);
+    }
+
+    #[test]
+    fn the_queue_grows_and_shrinks_like_a_stack() {
+        // 대기열(앙)에서 `ㅆ`, `ㅃ`, `ㅍ`를 쓴 프로그램과 악에서 쓴 프로그램의
+        // 크기가 상태마다 같다
+        for (queue, stack) in [
+            ("상반받빠파싸빠파마희", "삭반받빠파싸빠파마희"),
+            ("상반받빠파싸\n", "삭반받빠파싸\n"),
+        ] {
+            let on_queue = assert_sound(queue, 100);
+            let on_stack = assert_sound(stack, 100);
+            assert_eq!(on_queue.states.len(), on_stack.states.len());
+
+            for (state, depths) in &on_queue.states {
+                let twin = State {
+                    sel: if state.sel == 21 { 1 } else { state.sel },
+                    ..*state
+                };
+                let mut twin_depths = on_stack.states[&twin];
+                twin_depths.swap(1, 21);
+                assert_eq!(*depths, twin_depths, "{:?}", state);
+            }
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::constant::{Op, Synthesizer};
+use crate::hangul::{FILLER, assemble_jamo, jamo::_END, storage_name};
+use crate::sourcemap::{Pos, SourceMap};
+
+/// 어셈블리 한 줄의 명령
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instr {
+    /// 명령 하나가 셀 하나
+    Op(Op),
+    /// 0, 2..9가 아니면 여러 셀로 만든다
+    Push(i32),
+    /// 꺼낸 값이 0이거나 꺼낼 값이 없으면 이름표로
+    Brz(String),
+    Jmp(String),
+    Label(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    /// 1부터 센 줄 번호
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// 저장공간. 번호(0..=26), 이름(아, 악, ...) 또는 받침(ㄱ, ㄴ, ...)
+pub fn storage(arg: &str) -> Option<usize> {
+    if let Ok(idx) = arg.parse::<usize>() {
+        return (idx < 27).then_some(idx);
+    }
+
+    let mut chars = arg.chars();
+    let (Some(c), None) = (chars.next(), chars.next()) else {
+        return None;
+    };
+    (0..27).find(|&idx| storage_name(idx) == c || _END[idx] == (c, ' '))
+}
+
+/// 한 줄에 이름표(`이름:`)와 명령을 하나씩 쓸 수 있고, `;` 뒤는 주석.
+/// 명령마다 그 명령이 시작하는 줄과 칸
+pub fn parse(src: &str) -> Result<Vec<(Pos, Instr)>, Error> {
+    let mut instrs = vec![];
+
+    for (idx, line) in src.lines().enumerate() {
+        let line_no = idx + 1;
+        let err = |message: String| Error {
+            line: line_no,
+            message,
+        };
+        // `line`에서 `offset` 바이트 뒤의 공백을 건너뛴 칸
+        let column = |offset: usize| {
+            let rest = &line[offset..];
+            let offset = offset + rest.len() - rest.trim_start().len();
+            (line_no, line[..offset].chars().count() + 1)
+        };
+
+        let mut code = line.split(';').next().unwrap_or("");
+        let mut offset = 0;
+        if let Some((label, rest)) = code.split_once(':') {
+            let name = label.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(err(format!("invalid label {:?}", name)));
+            }
+            instrs.push((column(0), Instr::Label(name.to_string())));
+            offset = label.len() + 1;
+            code = rest;
+        }
+        let pos = column(offset);
+        let code = code.trim();
+        if code.is_empty() {
+            continue;
+        }
+
+        let words: Vec<&str> = code.split_whitespace().collect();
+        let arg = |n: usize| -> Result<&str, Error> {
+            if words.len() != n + 1 {
+                return Err(err(format!("{} takes {} argument(s)", words[0], n)));
+            }
+            Ok(words.get(1).copied().unwrap_or(""))
+        };
+        let op = |start: char, end: (char, char)| Instr::Op((start, end));
+        let none = (' ', ' ');
+
+        let instr = match words[0] {
+            "nop" => arg(0).map(|_| op('ㅇ', none))?,
+            "halt" => arg(0).map(|_| op('ㅎ', none))?,
+            "add" => arg(0).map(|_| op('ㄷ', none))?,
+            "mul" => arg(0).map(|_| op('ㄸ', none))?,
+            "sub" => arg(0).map(|_| op('ㅌ', none))?,
+            "div" => arg(0).map(|_| op('ㄴ', none))?,
+            "mod" => arg(0).map(|_| op('ㄹ', none))?,
+            "pop" => arg(0).map(|_| op('ㅁ', none))?,
+            "dup" => arg(0).map(|_| op('ㅃ', none))?,
+            "swap" => arg(0).map(|_| op('ㅍ', none))?,
+            "cmp" => arg(0).map(|_| op('ㅈ', none))?,
+            "print" | "input" => {
+                let end = match arg(1)? {
+                    "num" => ('ㅇ', ' '),
+                    "char" => ('ㅎ', ' '),
+                    kind => return Err(err(format!("expected num or char, got {:?}", kind))),
+                };
+                op(if words[0] == "print" { 'ㅁ' } else { 'ㅂ' }, end)
+            }
+            "push" => {
+                let n = arg(1)?;
+                Instr::Push(
+                    n.parse()
+                        .map_err(|_| err(format!("invalid integer {:?}", n)))?,
+                )
+            }
+            "sel" | "move" => {
+                let s = arg(1)?;
+                let idx = storage(s).ok_or_else(|| err(format!("unknown storage {:?}", s)))?;
+                op(if words[0] == "sel" { 'ㅅ' } else { 'ㅆ' }, _END[idx])
+            }
+            "brz" => Instr::Brz(arg(1)?.to_string()),
+            "jmp" => Instr::Jmp(arg(1)?.to_string()),
+            mnemonic => return Err(err(format!("unknown mnemonic {:?}", mnemonic))),
+        };
+        instrs.push((pos, instr));
+    }
+
+    Ok(instrs)
+}
+
+/// 셈한 값을 앞에 넣는 대기열(앙)
+const QUEUE: usize = 21;
+
+/// 명령마다 그 앞에서 선택되어 있을 수 있는 저장공간들(비트)
+fn selections(instrs: &[(Pos, Instr)]) -> Vec<u32> {
+    let labels: HashMap<&str, usize> = instrs
+        .iter()
+        .enumerate()
+        .filter_map(|(k, (_, instr))| match instr {
+            Instr::Label(name) => Some((name.as_str(), k)),
+            _ => None,
+        })
+        .collect();
+
+    let mut sels = vec![0u32; instrs.len()];
+    let mut work = vec![(0, 1u32)];
+    while let Some((k, sel)) = work.pop() {
+        if k >= instrs.len() || sels[k] | sel == sels[k] {
+            continue;
+        }
+        sels[k] |= sel;
+        let sel = sels[k];
+
+        match &instrs[k].1 {
+            Instr::Op(('ㅅ', end)) => {
+                let idx = _END.iter().position(|e| e == end).unwrap_or(0);
+                work.push((k + 1, 1 << idx));
+            }
+            Instr::Op(('ㅎ', _)) => {}
+            Instr::Jmp(name) => work.extend(labels.get(name.as_str()).map(|&to| (to, sel))),
+            Instr::Brz(name) => {
+                work.extend(labels.get(name.as_str()).map(|&to| (to, sel)));
+                work.push((k + 1, sel));
+            }
+            _ => work.push((k + 1, sel)),
+        }
+    }
+    sels
+}
+
+/// 명령을 한 열(x = 1)에 위에서 아래로 늘어놓는다.
+///
+/// - `jmp`는 오른쪽으로 나가 점프마다 정한 세로 길(lane)을 타고 이름표의 행까지
+///   간 뒤 왼쪽으로 돌아온다. 길이 겹치지 않는 점프는 같은 열을 나눠 쓴다.
+/// - `brz`는 `ㅊ`을 왼쪽으로 보내서, 0이면 반사되어 오른쪽 길로 나가고 아니면
+///   x = 0 열을 돌아 다음 행으로 들어온다.
+/// - 길이 엇갈리는 칸은 방향을 바꾸지 않는 `FILLER`
+///
+/// 끝에 닿으면 `halt`처럼 끝난다. `push`가 0, 2..9가 아니면 셈을 쓰므로
+/// 대기열(앙)이 선택되어 있으면 `아`에서 만들어 옮긴다. 대기열일 수도 있고
+/// 아닐 수도 있으면 오류
+pub fn assemble(src: &str) -> Result<String, Error> {
+    assemble_with_map(src).map(|(grid, _)| grid)
+}
+
+/// `assemble`과 같고, 셀마다 그 셀을 만든 어셈블리 명령의 줄과 칸을 함께
+pub fn assemble_with_map(src: &str) -> Result<(String, SourceMap), Error> {
+    build(src, true)
+}
+
+/// `assemble_with_map`과 같지만 끝에 닿으면 멈추지 않고 아래 가장자리로 나간다.
+/// `link`에 넘길 모듈
+pub fn assemble_module(src: &str) -> Result<(String, SourceMap), Error> {
+    build(src, false)
+}
+
+fn build(src: &str, halt: bool) -> Result<(String, SourceMap), Error> {
+    let instrs = parse(src)?;
+    let sels = selections(&instrs);
+    let mut synth = Synthesizer::default();
+
+    // x = 1 열의 셀과 x = 0 열의 셀. 처음 행은 (0, 0)에서 들어오는 길
+    let mut column: Vec<(char, Option<char>)> = vec![('우', Some('아'))];
+    // 행마다 만든 명령의 자리
+    let mut origins: Vec<Option<Pos>> = vec![None];
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    // (자리, 나가는 행, 이름표)
+    let mut jumps: Vec<(Pos, usize, &str)> = vec![];
+
+    for (&(pos, ref instr), &sel) in instrs.iter().zip(&sels) {
+        match instr {
+            Instr::Op((start, end)) => column.push((assemble_jamo(*start, 'ㅜ', *end), None)),
+            Instr::Push(n) => {
+                let ops = match synth.synthesize(*n) {
+                    ops if ops.len() == 1 || sel & 1 << QUEUE == 0 => ops,
+                    ops if sel == 1 << QUEUE => [
+                        vec![('ㅅ', _END[0])],
+                        ops,
+                        vec![('ㅆ', _END[QUEUE]), ('ㅅ', _END[QUEUE])],
+                    ]
+                    .concat(),
+                    _ => {
+                        return Err(Error {
+                            line: pos.0,
+                            message: format!(
+                                "push {} may run with the queue {} selected",
+                                n,
+                                storage_name(QUEUE)
+                            ),
+                        });
+                    }
+                };
+                for (start, end) in ops {
+                    column.push((assemble_jamo(start, 'ㅜ', end), None));
+                }
+            }
+            Instr::Label(name) => {
+                if labels.insert(name, column.len()).is_some() {
+                    return Err(Error {
+                        line: pos.0,
+                        message: format!("duplicate label {:?}", name),
+                    });
+                }
+                column.push(('우', None));
+            }
+            Instr::Jmp(name) => {
+                jumps.push((pos, column.len(), name));
+                column.push(('아', None));
+            }
+            Instr::Brz(name) => {
+                jumps.push((pos, column.len(), name));
+                column.push(('처', Some('우')));
+                column.push(('우', Some('아')));
+            }
+        }
+        origins.resize(column.len(), Some(pos));
+    }
+
+    let falls_through = !matches!(
+        instrs.last(),
+        Some((_, Instr::Jmp(_))) | Some((_, Instr::Op(('ㅎ', _))))
+    );
+    if falls_through && halt {
+        column.push(('후', None));
+    }
+    origins.resize(column.len(), None);
+
+    // 겹치지 않는 점프끼리 같은 세로 길을 쓴다
+    let mut routes: Vec<(usize, usize)> = vec![];
+    for &(pos, from, name) in &jumps {
+        let to = *labels.get(name).ok_or_else(|| Error {
+            line: pos.0,
+            message: format!("undefined label {:?}", name),
+        })?;
+        routes.push((from, to));
+    }
+    let mut order: Vec<usize> = (0..routes.len()).collect();
+    order.sort_by_key(|&k| routes[k].0.min(routes[k].1));
+    let mut lane_ends: Vec<usize> = vec![];
+    let mut lanes = vec![0; routes.len()];
+    for k in order {
+        let (from, to) = routes[k];
+        let (lo, hi) = (from.min(to), from.max(to));
+        let lane = match lane_ends.iter().position(|&end| end < lo) {
+            Some(lane) => lane,
+            None => {
+                lane_ends.push(0);
+                lane_ends.len() - 1
+            }
+        };
+        lane_ends[lane] = hi;
+        lanes[k] = lane;
+    }
+
+    let mut map = SourceMap::default();
+
+    let width = 2 + lane_ends.len();
+    let mut grid = vec![vec![FILLER; width]; column.len()];
+    for (y, &(cell, left)) in column.iter().enumerate() {
+        grid[y][1] = cell;
+        if let Some(left) = left {
+            grid[y][0] = left;
+        }
+        if let Some(pos) = origins[y] {
+            map.insert((1, y as i32), pos);
+            if left.is_some() {
+                map.insert((0, y as i32), pos);
+            }
+        }
+    }
+    for ((&(from, to), &lane), &(pos, _, _)) in routes.iter().zip(&lanes).zip(&jumps) {
+        let x = 2 + lane;
+        grid[from][x] = if to > from { '우' } else { '오' };
+        grid[to][x] = '어';
+        map.insert((x as i32, from as i32), pos);
+        map.insert((x as i32, to as i32), pos);
+    }
+
+    let grid = grid
+        .iter()
+        .map(|row| row.iter().collect::<String>() + "\n")
+        .collect();
+    Ok((grid, map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aheui::Aheui;
+    use crate::equiv::run;
+
+    fn output(src: &str) -> String {
+        let grid = assemble(src).unwrap();
+        run(&Aheui::new(grid), "", 100_000).output
+    }
+
+    #[test]
+    fn pushes_built_values_onto_the_queue_in_order() {
+        let src =
+            "sel 앙\npush 1\npush 100\nmove 아\nmove 아\nsel 아\nprint num\nprint num\nhalt\n";
+        assert_eq!(output(src), "1001");
+    }
+
+    #[test]
+    fn keeps_the_scratch_stack_when_pushing_onto_the_queue() {
+        let src = "push 7\nsel 앙\npush 1000\nmove 아\nsel 아\nprint num\nprint num\n";
+        assert_eq!(output(src), "10007");
+    }
+
+    #[test]
+    fn follows_the_selection_through_jumps() {
+        let src = "sel 앙\njmp there\nsel 아\nthere:\npush 100\nmove 아\nsel 아\nprint num\n";
+        assert_eq!(output(src), "100");
+    }
+
+    #[test]
+    fn maps_cells_to_the_instruction_column() {
+        let (_, map) = assemble_with_map("  push 3\nloop:   dup ; copy\n   jmp loop\n").unwrap();
+        assert_eq!(map.get((1, 1)), Some((1, 3)));
+        assert_eq!(map.get((1, 2)), Some((2, 1)));
+        assert_eq!(map.get((1, 3)), Some((2, 9)));
+        assert_eq!(map.get((1, 4)), Some((3, 4)));
+    }
+
+    #[test]
+    fn rejects_a_push_that_may_or_may_not_hit_the_queue() {
+        let src = "input num\nbrz skip\nsel 앙\nskip:\npush 100\n";
+        let err = assemble(src).unwrap_err();
+        assert_eq!(err.line, 5);
+    }
+}
@@ -1,149 +1,387 @@
-use std::io;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
 
+use clap::Parser;
+use color_eyre::Result;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
 use ratatui::{
     DefaultTerminal, Frame,
-    buffer::Buffer,
-    layout::{Constraint, Direction, Layout, Rect},
-    style::Stylize,
+    layout::{Constraint, Layout, Rect},
+    style::{Style, Stylize},
     symbols::border,
-    text::{Line, Text},
-    widgets::{Block, Paragraph, Widget},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Wrap},
 };
 
-fn main() -> io::Result<()> {
+use oxidaheuize::aheui::Aheui;
+use oxidaheuize::breakpoint::{self, Breakpoint};
+use oxidaheuize::hangul::storage_name;
+use oxidaheuize::sourcemap::SourceMap;
+
+/// 계속 실행할 때 키 입력을 확인하는 간격(걸음)
+const BATCH: usize = 10_000;
+
+/// Step through an Aheui program in the terminal
+#[derive(Parser)]
+struct Args {
+    /// Aheui source code
+    file: PathBuf,
+
+    /// Input for the program; without it every read sees end of input
+    #[arg(long, short)]
+    input: Option<PathBuf>,
+
+    /// Source map from a generator; defaults to FILE.map when it exists
+    #[arg(long, value_name = "MAP")]
+    source_map: Option<PathBuf>,
+
+    /// Breakpoint or tracepoint, as for `oxidaheuize --break`; <B> adds more
+    #[arg(long = "break", value_name = "SPEC")]
+    breakpoints: Vec<Breakpoint>,
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    let args = Args::parse();
+
+    let mut aheui = Aheui::new(fs::read_to_string(&args.file)?);
+    aheui.debug(true);
+    // 따로 주지 않으면 옆에 있는 `<file>.map`
+    let map_path = args.source_map.or_else(|| {
+        let mut path = args.file.clone().into_os_string();
+        path.push(".map");
+        Some(PathBuf::from(path)).filter(|p| p.exists())
+    });
+    if let Some(path) = map_path {
+        let map =
+            SourceMap::parse(&fs::read_to_string(path)?).map_err(color_eyre::eyre::Error::msg)?;
+        aheui.set_source_map(Some(map));
+    }
+    let input = args
+        .input
+        .map(fs::read_to_string)
+        .transpose()?
+        .unwrap_or_default();
+
     let mut terminal = ratatui::init();
-    let app_result = App::default().run(&mut terminal);
+    let mut app = App::new(args.file.display().to_string(), aheui, input);
+    app.breakpoints = args.breakpoints;
+    let result = app.run(&mut terminal);
     ratatui::restore();
-    app_result
+    result
 }
 
-#[derive(Debug, Default)]
 pub struct App {
-    counter: u8,
+    name: String,
+    aheui: Aheui,
+    input: String,
+    breakpoints: Vec<Breakpoint>,
+    /// 추적점의 메시지와 잘못 쓴 중단점
+    log: Vec<String>,
+    /// 지금 자리에서 멈추게 한 중단점. 이어 갈 때는 다시 보지 않는다
+    stopped: Option<usize>,
+    /// `b`로 입력 중인 중단점
+    prompt: Option<String>,
+    /// `c`로 계속 실행하는 중
+    running: bool,
     exit: bool,
 }
 
 impl App {
-    /// runs the application's main loop until the user quits
-    pub fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
-        while !self.exit {
-            terminal.draw(|frame| {
-                let outter_layout = Layout::default()
-                    .direction(Direction::Vertical)
-                    .constraints(vec![Constraint::Percentage(70), Constraint::Percentage(30)])
-                    .split(frame.area());
-
-                let layout = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints(vec![Constraint::Percentage(70), Constraint::Percentage(30)])
-                    .split(outter_layout[0]);
-
-                self.draw(frame, &outter_layout[1]);
-                self.draw(frame, &layout[0]);
-                self.draw(frame, &layout[1]);
-            })?;
-            self.handle_events()?;
+    pub fn new(name: String, mut aheui: Aheui, input: String) -> Self {
+        aheui.set_input(input.clone());
+        Self {
+            name,
+            aheui,
+            input,
+            breakpoints: vec![],
+            log: vec![],
+            stopped: None,
+            prompt: None,
+            running: false,
+            exit: false,
         }
-        Ok(())
     }
 
-    fn draw(&self, frame: &mut Frame, &rect: &Rect) {
-        frame.render_widget(self, rect);
-    }
+    pub fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.exit {
+            terminal.draw(|frame| self.draw(frame))?;
 
-    fn handle_events(&mut self) -> io::Result<()> {
-        match event::read()? {
-            // it's important to check that the event is a key press event as
-            // crossterm also emits key release and repeat events on Windows.
-            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                self.handle_key_event(key_event)
+            if self.running {
+                for _ in 0..BATCH {
+                    if !self.step(true) {
+                        self.running = false;
+                        break;
+                    }
+                }
+                // 실행 중에도 멈추거나 끝낼 수 있게
+                if event::poll(Duration::ZERO)? {
+                    self.handle_event(event::read()?);
+                }
+            } else {
+                self.handle_event(event::read()?);
             }
-            _ => {}
-        };
+        }
         Ok(())
     }
 
-    fn handle_key_event(&mut self, key_event: KeyEvent) {
-        match key_event.code {
-            KeyCode::Char('q') => self.exit(),
-            KeyCode::Left => self.decrement_counter(),
-            KeyCode::Right => self.increment_counter(),
-            _ => {}
+    /// 한 걸음. 이미 끝났거나 `stop`이고 중단점에 걸렸으면 `false`
+    fn step(&mut self, stop: bool) -> bool {
+        if self.aheui.ended || self.aheui.src_mat.0 == 0 || self.aheui.src_mat.1 == 0 {
+            return false;
         }
+        if self.stopped.take().is_none() {
+            let log = &mut self.log;
+            let hit = breakpoint::check(&self.aheui, &self.breakpoints, |m| log.push(m));
+            if let (true, Some(idx)) = (stop, hit) {
+                self.stopped = Some(idx);
+                return false;
+            }
+        }
+        self.aheui.next();
+        true
     }
 
-    fn exit(&mut self) {
-        self.exit = true;
+    fn restart(&mut self) {
+        self.aheui.reset();
+        self.aheui.set_input(self.input.clone());
+        self.log.clear();
+        self.stopped = None;
+        self.running = false;
     }
 
-    fn increment_counter(&mut self) {
-        self.counter += 1;
+    fn handle_event(&mut self, event: Event) {
+        // crossterm은 Windows에서 키를 뗄 때도 알리므로 누를 때만
+        if let Event::Key(key) = event
+            && key.kind == KeyEventKind::Press
+        {
+            self.handle_key_event(key);
+        }
     }
 
-    fn decrement_counter(&mut self) {
-        self.counter -= 1;
+    fn handle_key_event(&mut self, key: KeyEvent) {
+        if let Some(prompt) = &mut self.prompt {
+            match key.code {
+                KeyCode::Enter => {
+                    match prompt.parse::<Breakpoint>() {
+                        Ok(bp) => self.breakpoints.push(bp),
+                        Err(err) => self.log.push(format!("error: {}", err)),
+                    }
+                    self.prompt = None;
+                }
+                KeyCode::Esc => self.prompt = None,
+                KeyCode::Backspace => {
+                    prompt.pop();
+                }
+                KeyCode::Char(c) => prompt.push(c),
+                _ => {}
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.exit = true,
+            KeyCode::Char('s') | KeyCode::Char(' ') | KeyCode::Right => {
+                self.running = false;
+                self.step(false);
+            }
+            KeyCode::Char('c') => self.running = !self.running,
+            KeyCode::Char('r') => self.restart(),
+            KeyCode::Char('b') => {
+                self.running = false;
+                self.prompt = Some(String::new());
+            }
+            KeyCode::Char('d') => {
+                self.breakpoints.pop();
+                self.stopped = None;
+            }
+            _ => {}
+        }
     }
-}
 
-impl Widget for &App {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let title = Line::from(" Counter App Tutorial ".bold());
-        let instructions = Line::from(vec![
-            " Decrement ".into(),
-            "<Left>".blue().bold(),
-            " Increment ".into(),
-            "<Right>".blue().bold(),
+    fn draw(&self, frame: &mut Frame) {
+        let [top, bottom] =
+            Layout::vertical([Constraint::Percentage(70), Constraint::Percentage(30)])
+                .areas(frame.area());
+        let [grid, storages] =
+            Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)]).areas(top);
+
+        let [output, breakpoints] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(bottom);
+
+        self.draw_grid(frame, grid);
+        self.draw_storages(frame, storages);
+        self.draw_output(frame, output);
+        self.draw_breakpoints(frame, breakpoints);
+    }
+
+    fn draw_grid(&self, frame: &mut Frame, area: Rect) {
+        let aheui = &self.aheui;
+        let title = Line::from(format!(" {} ", self.name).bold());
+        let keys = Line::from(vec![
+            " Step ".into(),
+            "<S>".blue().bold(),
+            " Continue ".into(),
+            "<C>".blue().bold(),
+            " Restart ".into(),
+            "<R>".blue().bold(),
+            " Break ".into(),
+            "<B>".blue().bold(),
+            " Delete ".into(),
+            "<D>".blue().bold(),
             " Quit ".into(),
             "<Q> ".blue().bold(),
         ]);
         let block = Block::bordered()
             .title(title.centered())
-            .title_bottom(instructions.centered())
+            .title_bottom(keys.centered())
             .border_set(border::THICK);
 
-        let counter_text = Text::from(vec![
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-            Line::from(vec!["Value: ".into(), self.counter.to_string().yellow()]),
-        ]);
+        // 셀 하나가 두 칸. 커서가 보이도록 옮긴다
+        let inner = block.inner(area);
+        let cols = (inner.width as usize / 2).max(1);
+        let rows = (inner.height as usize).max(1);
+        let scroll = |pos: i32, len: usize, view: usize| {
+            (pos.max(0) as usize)
+                .saturating_sub(view / 2)
+                .min(len.saturating_sub(view))
+        };
+        let x0 = scroll(aheui.cursor.0, aheui.src_mat.0, cols);
+        let y0 = scroll(aheui.cursor.1, aheui.src_mat.1, rows);
+
+        let lines: Vec<Line> = aheui
+            .src_map
+            .iter()
+            .enumerate()
+            .skip(y0)
+            .take(rows)
+            .map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .skip(x0)
+                    .take(cols)
+                    .map(|(x, cell)| {
+                        let pos = (x as i32, y as i32);
+                        // 한글이 아닌 칸도 두 칸을 차지하게
+                        let text = match cell.3.is_ascii() {
+                            true => format!("{} ", cell.3),
+                            false => cell.3.to_string(),
+                        };
+                        if pos == aheui.cursor {
+                            Span::styled(text, Style::new().black().on_blue())
+                        } else if pos == aheui.prev && aheui.step > 0 {
+                            Span::styled(text, Style::new().white().on_red())
+                        } else {
+                            Span::raw(text)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .into()
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_storages(&self, frame: &mut Frame, area: Rect) {
+        let aheui = &self.aheui;
+        let lines: Vec<Line> = aheui
+            .stacks
+            .iter()
+            .enumerate()
+            .map(|(idx, items)| {
+                // 왼쪽이 위(대기열은 앞)
+                let values: Vec<String> = items.iter().map(i32::to_string).collect();
+                let text = format!("{} {}", storage_name(idx), values.join(" "));
+                if idx == aheui.sel {
+                    Line::from(format!(">{}", text).yellow().bold())
+                } else {
+                    Line::from(format!(" {}", text))
+                }
+            })
+            .collect();
+
+        let block = Block::bordered()
+            .title(Line::from(" Storages ".bold()).centered())
+            .border_set(border::THICK);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_output(&self, frame: &mut Frame, area: Rect) {
+        let aheui = &self.aheui;
+        let state = match (&aheui.exit_code, self.running, self.stopped) {
+            (Some(code), _, _) => format!("ended: {:?}", code),
+            (None, true, _) => "running".to_string(),
+            (None, false, Some(idx)) => format!("breakpoint {}", idx + 1),
+            (None, false, None) => "paused".to_string(),
+        };
+        let origin = aheui
+            .origin(aheui.cursor)
+            .map(|(line, col)| format!(", line {} column {}", line, col))
+            .unwrap_or_default();
+        let status = Line::from(format!(
+            " step {}, cursor ({}, {}), dir ({}, {}){} ",
+            aheui.step, aheui.cursor.0, aheui.cursor.1, aheui.dir.0, aheui.dir.1, origin
+        ));
+
+        let block = Block::bordered()
+            .title(Line::from(format!(" Output - {} ", state).bold()).centered())
+            .title_bottom(status.centered())
+            .border_set(border::THICK);
+
+        // 마지막 줄이 보이도록
+        let inner = block.inner(area);
+        let width = inner.width.max(1) as usize;
+        let height: usize = aheui
+            .result
+            .split('\n')
+            .map(|line| line.chars().count().max(1).div_ceil(width))
+            .sum();
+        let scroll = height.saturating_sub(inner.height as usize) as u16;
 
-        Paragraph::new(counter_text)
-            .centered()
-            .block(block)
-            .render(area, buf);
+        frame.render_widget(
+            Paragraph::new(aheui.result.as_str())
+                .wrap(Wrap { trim: false })
+                .scroll((scroll, 0))
+                .block(block),
+            area,
+        );
+    }
+
+    fn draw_breakpoints(&self, frame: &mut Frame, area: Rect) {
+        let mut lines: Vec<Line> = self
+            .breakpoints
+            .iter()
+            .enumerate()
+            .map(|(idx, bp)| {
+                let text = format!("{} {}", idx + 1, bp);
+                match self.stopped == Some(idx) {
+                    true => Line::from(text.yellow().bold()),
+                    false => Line::from(text),
+                }
+            })
+            .collect();
+
+        // 남은 자리에 최근 메시지
+        let inner = Block::bordered().inner(area);
+        let room = (inner.height as usize).saturating_sub(lines.len());
+        let skip = self.log.len().saturating_sub(room);
+        lines.extend(
+            self.log[skip..]
+                .iter()
+                .map(|m| Line::from(m.as_str().dark_gray())),
+        );
+
+        let bottom = match &self.prompt {
+            Some(prompt) => Line::from(format!(" break: {}_ ", prompt).yellow()),
+            None => Line::from(""),
+        };
+        let block = Block::bordered()
+            .title(Line::from(" Breakpoints ".bold()).centered())
+            .title_bottom(bottom)
+            .border_set(border::THICK);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
     }
 }
@@ -0,0 +1,327 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+
+use crate::asm::{Error, assemble_with_map};
+use crate::sourcemap::{Pos, SourceMap};
+
+/// 셀 값은 0..=255에서 감싼다
+const CELL: i32 = 256;
+
+/// 괄호의 짝. 맞지 않으면 그 괄호의 줄
+fn brackets(src: &str) -> Result<Vec<Option<usize>>, Error> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut pairs = vec![None; chars.len()];
+    let mut open: Vec<(usize, usize)> = vec![];
+    let mut line = 1;
+
+    for (idx, &c) in chars.iter().enumerate() {
+        match c {
+            '\n' => line += 1,
+            '[' => open.push((idx, line)),
+            ']' => {
+                let (start, _) = open.pop().ok_or_else(|| Error {
+                    line,
+                    message: "unmatched ']'".to_string(),
+                })?;
+                pairs[start] = Some(idx);
+                pairs[idx] = Some(start);
+            }
+            _ => {}
+        }
+    }
+    if let Some(&(_, line)) = open.last() {
+        return Err(Error {
+            line,
+            message: "unmatched '['".to_string(),
+        });
+    }
+
+    Ok(pairs)
+}
+
+/// 기준 인터프리터. 테이프는 양쪽으로 끝이 없고, 입력이 끝나면 셀을 그대로 둔다.
+/// 글자는 코드를 256으로 나눈 나머지로 읽는다. `steps` 안에 끝나지 않으면 `None`
+pub fn run(src: &str, input: &str, steps: usize) -> Result<Option<String>, Error> {
+    let code: Vec<char> = src.chars().collect();
+    let pairs = brackets(src)?;
+    let mut input = input.chars();
+    let mut tape: VecDeque<i32> = VecDeque::from([0]);
+    let mut head = 0;
+    let mut out = String::new();
+
+    let mut pc = 0;
+    let mut step = 0;
+    while pc < code.len() && step < steps {
+        match code[pc] {
+            '+' => tape[head] = (tape[head] + 1) % CELL,
+            '-' => tape[head] = (tape[head] + CELL - 1) % CELL,
+            '>' => {
+                head += 1;
+                if head == tape.len() {
+                    tape.push_back(0);
+                }
+            }
+            '<' => {
+                if head == 0 {
+                    tape.push_front(0);
+                } else {
+                    head -= 1;
+                }
+            }
+            '.' => out.push(char::from_u32(tape[head] as u32).unwrap_or(' ')),
+            ',' => {
+                if let Some(c) = input.next() {
+                    tape[head] = c as i32 % CELL;
+                }
+            }
+            '[' if tape[head] == 0 => pc = pairs[pc].unwrap(),
+            ']' if tape[head] != 0 => pc = pairs[pc].unwrap(),
+            _ => {}
+        }
+        pc += 1;
+        step += 1;
+    }
+
+    Ok((pc >= code.len()).then_some(out))
+}
+
+/// 어셈블리로 옮긴다.
+///
+/// - 테이프: `아`의 맨 위가 현재 셀, 그 아래가 오른쪽 셀들. `악`의 맨 위가
+///   바로 왼쪽 셀. 두 스택 바닥에는 0을 두고, 셀은 값 + 1로 넣어서 0이 보이면
+///   테이프의 끝이므로 새 셀을 만든다
+/// - 입력: `ㅂㅎ`은 한 줄을 통째로 넣으므로 `앙`을 줄 버퍼로 쓴다. 맨 끝에 0을
+///   두고 0이 앞에 오면 다음 줄을 읽는다. 읽어도 0이 앞에 있으면 입력의 끝이고
+///   셀은 그대로
+///
+/// 입력에 NUL 글자가 있으면 줄의 끝으로 본다
+pub fn to_asm(src: &str) -> Result<String, Error> {
+    translate(src).map(|(asm, _)| asm)
+}
+
+/// 어셈블리와 그 줄마다 만든 명령의 자리
+fn translate(src: &str) -> Result<(String, Vec<Pos>), Error> {
+    brackets(src)?;
+    let mut code: Vec<(char, Pos)> = vec![];
+    for (l, line) in src.lines().enumerate() {
+        for (c, chr) in line.chars().enumerate() {
+            if "+-<>.,[]".contains(chr) {
+                code.push((chr, (l + 1, c + 1)));
+            }
+        }
+    }
+
+    let mut out = String::new();
+    let mut origins: Vec<Pos> = vec![];
+    // 지금 옮기는 명령의 자리
+    let origin = Cell::new((1, 1));
+    let mut emit = |line: &str| {
+        out += line;
+        out += "\n";
+        origins.push(origin.get());
+    };
+    // 이름표가 겹치지 않도록 명령마다 새 번호
+    let mut labels = 0;
+    let mut loops: Vec<usize> = vec![];
+
+    emit("sel 앙");
+    emit("push 0");
+    emit("sel 악");
+    emit("push 0");
+    emit("sel 아");
+    emit("push 0");
+    emit("push 1");
+
+    let mut i = 0;
+    while i < code.len() {
+        let (c, pos) = code[i];
+        origin.set(pos);
+        match c {
+            '+' | '-' => {
+                let mut delta = 0;
+                while let Some(&(c, _)) = code.get(i) {
+                    match c {
+                        '+' => delta += 1,
+                        '-' => delta -= 1,
+                        _ => break,
+                    }
+                    i += 1;
+                }
+                let delta = (delta % CELL + CELL) % CELL;
+                if delta > 1 {
+                    emit(&format!("push {}", delta - 1));
+                    emit("add");
+                }
+                if delta != 0 {
+                    emit(&format!("push {}", CELL));
+                    emit("mod");
+                    emit("push 1");
+                    emit("add");
+                }
+                continue;
+            }
+            // [-], [+]는 0으로
+            '[' if matches!(code.get(i + 1..i + 3), Some([('-' | '+', _), (']', _)])) => {
+                emit("pop");
+                emit("push 1");
+                i += 3;
+                continue;
+            }
+            '>' | '<' => {
+                let k = labels;
+                labels += 1;
+                // 옮겨 올 스택의 맨 위가 바닥의 0이면 새 셀
+                if c == '>' {
+                    emit("move 악");
+                } else {
+                    emit("sel 악");
+                }
+                emit("dup");
+                emit(&format!("brz tape_end_{}", k));
+                emit(&format!("jmp tape_ok_{}", k));
+                emit(&format!("tape_end_{}:", k));
+                emit("push 1");
+                emit(&format!("tape_ok_{}:", k));
+                if c == '<' {
+                    emit("move 아");
+                    emit("sel 아");
+                }
+            }
+            '.' => {
+                emit("dup");
+                emit("push 1");
+                emit("sub");
+                emit("print char");
+            }
+            ',' => {
+                let k = labels;
+                labels += 1;
+                emit("sel 앙");
+                emit("dup");
+                emit(&format!("brz read_{}", k));
+                emit(&format!("got_{}:", k));
+                emit("sel 아");
+                emit("pop");
+                emit("sel 앙");
+                emit("move 아");
+                emit("sel 아");
+                emit(&format!("push {}", CELL));
+                emit("mod");
+                emit("push 1");
+                emit("add");
+                emit(&format!("jmp read_done_{}", k));
+                emit(&format!("read_{}:", k));
+                emit("pop");
+                emit("input char");
+                emit("push 0");
+                emit("dup");
+                emit(&format!("brz eof_{}", k));
+                emit(&format!("jmp got_{}", k));
+                emit(&format!("eof_{}:", k));
+                emit("sel 아");
+                emit(&format!("read_done_{}:", k));
+            }
+            '[' => {
+                let k = labels;
+                labels += 1;
+                loops.push(k);
+                emit(&format!("loop_{}:", k));
+                emit("dup");
+                emit("push 1");
+                emit("sub");
+                emit(&format!("brz loop_end_{}", k));
+            }
+            _ => {
+                let k = loops.pop().unwrap();
+                emit(&format!("jmp loop_{}", k));
+                emit(&format!("loop_end_{}:", k));
+            }
+        }
+        i += 1;
+    }
+    if let Some(&(_, pos)) = code.last() {
+        origin.set(pos);
+    }
+    emit("halt");
+
+    Ok((out, origins))
+}
+
+/// 아희 격자로
+pub fn compile(src: &str) -> Result<String, Error> {
+    compile_with_map(src).map(|(grid, _)| grid)
+}
+
+/// `compile`과 같고, 셀마다 그 셀을 만든 명령의 자리를 함께
+pub fn compile_with_map(src: &str) -> Result<(String, SourceMap), Error> {
+    let (asm, origins) = translate(src)?;
+    let (grid, map) = assemble_with_map(&asm)?;
+    Ok((grid, map.compose(&origins)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aheui::{Aheui, ExitCode};
+    use crate::equiv;
+
+    /// 기준 인터프리터와 같은 출력을 내는지
+    fn agrees(src: &str, input: &str) {
+        let expected = run(src, input, 1_000_000).unwrap().unwrap();
+        let grid = compile(src).unwrap();
+        let outcome = equiv::run(&Aheui::new(grid), input, 10_000_000);
+        assert_eq!(outcome.output, expected, "{:?} on {:?}", src, input);
+        assert!(
+            matches!(outcome.exit, Some(ExitCode::Success(_))),
+            "{:?}",
+            outcome.exit
+        );
+    }
+
+    #[test]
+    fn maps_the_print_cell_to_its_command() {
+        let (grid, map) = compile_with_map("+++\n  .+.").unwrap();
+        let cell = crate::sourcemap::first_print(&grid, "");
+        assert_eq!(map.get(cell), Some((2, 3)));
+    }
+
+    #[test]
+    fn prints_hello_world() {
+        agrees(
+            "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.",
+            "",
+        );
+    }
+
+    #[test]
+    fn runs_nested_loops() {
+        agrees("++++[>+++[>++++<-]<-]>>.", "");
+        agrees("++[>++[>++[>++++++<-]<-]<-]>>>+.", "");
+    }
+
+    #[test]
+    fn leaves_the_cell_at_end_of_input() {
+        agrees(",.,.,.", "a");
+        agrees("+++,.", "");
+    }
+
+    #[test]
+    fn reads_across_lines() {
+        agrees(",.,.,.,.,.", "ab\ncd\n");
+        agrees(">,----------[++++++++++>,----------]<[.<]", "stressed\n");
+    }
+
+    #[test]
+    fn grows_the_tape_both_ways() {
+        agrees(
+            "<<+++[>++++<-]>[>>>+<<<-]>>>++++++++++++++++++++++++++++++++++++.",
+            "",
+        );
+    }
+
+    #[test]
+    fn wraps_cell_values() {
+        agrees("-.+.", "");
+        agrees("-[--->+<]>.", "");
+    }
+}
@@ -0,0 +1,400 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::aheui::{Aheui, turn};
+use crate::analysis::required_depth;
+use crate::asm::storage;
+use crate::hangul::storage_name;
+
+/// 어느 걸음에서 보는지
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    /// 모든 걸음
+    Any,
+    Cell(i32, i32),
+    /// 닿소리(`ㅎ`) 또는 음절 하나(`망`)
+    Op(char),
+    /// 이만큼 실행한 뒤
+    Step(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    Step,
+    /// 저장공간의 길이. `None`이면 선택된 것
+    Len(Option<usize>),
+    /// 저장공간의 맨 위(대기열은 맨 앞). 비었으면 조건이 거짓
+    Top(Option<usize>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+}
+
+const CMPS: [(Cmp, &str); 6] = [
+    (Cmp::Lt, "<"),
+    (Cmp::Le, "<="),
+    (Cmp::Eq, "=="),
+    (Cmp::Ne, "!="),
+    (Cmp::Ge, ">="),
+    (Cmp::Gt, ">"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Compare(Value, Cmp, i64),
+    /// 이 셀에서 방향이 거꾸로 바뀐다
+    Reversed,
+}
+
+/// `위치 [if 조건 [and 조건]...] [log 메시지]`.
+///
+/// 위치는 `x,y`, `op ㅎ`, `step 100`, `any`. 조건은 `reversed` 또는
+/// `len 앗 > 100`, `top == 0`처럼 `step`, `len [저장공간]`, `top [저장공간]`을
+/// 수와 비교한다. `log`가 있으면 멈추지 않고 메시지만 남기는 추적점이고,
+/// 메시지의 `{step}`, `{x}`, `{y}`, `{cell}`, `{sel}`, `{top}`, `{len}`을 채운다.
+///
+/// 셀을 실행하기 전에 보므로 조건도 실행하기 전의 상태에 대한 것
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub location: Location,
+    pub conditions: Vec<Condition>,
+    pub log: Option<String>,
+}
+
+impl Breakpoint {
+    /// 지금 셀을 실행하기 전에 걸리는지
+    pub fn hits(&self, aheui: &Aheui) -> bool {
+        let chr = aheui.current();
+        let at = match self.location {
+            Location::Any => true,
+            Location::Cell(x, y) => aheui.cursor == (x, y),
+            Location::Op(c) => chr.0 == c || chr.3 == c,
+            Location::Step(n) => aheui.step == n,
+        };
+        at && self.conditions.iter().all(|c| holds(c, aheui))
+    }
+
+    /// 추적점이면 채운 메시지
+    pub fn message(&self, aheui: &Aheui) -> Option<String> {
+        let top = top(aheui, aheui.sel).map_or("-".to_string(), |v| v.to_string());
+        let text = self
+            .log
+            .as_ref()?
+            .replace("{step}", &aheui.step.to_string())
+            .replace("{x}", &aheui.cursor.0.to_string())
+            .replace("{y}", &aheui.cursor.1.to_string())
+            .replace("{cell}", &aheui.current().3.to_string())
+            .replace("{sel}", &storage_name(aheui.sel).to_string())
+            .replace("{top}", &top)
+            .replace("{len}", &aheui.stacks[aheui.sel].len().to_string());
+        Some(text)
+    }
+}
+
+fn top(aheui: &Aheui, sel: usize) -> Option<i32> {
+    aheui.stacks[sel].front().copied()
+}
+
+/// 지금 셀을 실행하면 방향이 거꾸로 되는지. 인터프리터처럼 홀소리로 돌린 뒤
+/// 값이 모자라거나, 없는 명령이거나, `ㅊ`이 0을 꺼내면 뒤집는다
+fn reverses(aheui: &Aheui) -> bool {
+    let chr = aheui.current();
+    if matches!(chr.0, ' ' | 'ㅎ') {
+        return false;
+    }
+
+    let stack = &aheui.stacks[aheui.sel];
+    let reflect = match chr.0 {
+        'ㅉ' | 'ㅋ' => true,
+        'ㅊ' => stack.front().is_none_or(|&v| v == 0),
+        _ => required_depth(chr).is_some_and(|n| stack.len() < n),
+    };
+    let dir = turn(chr.1, aheui.dir);
+    let dir = if reflect { (-dir.0, -dir.1) } else { dir };
+    aheui.dir != (0, 0) && dir == (-aheui.dir.0, -aheui.dir.1)
+}
+
+fn holds(condition: &Condition, aheui: &Aheui) -> bool {
+    let (value, cmp, rhs) = match *condition {
+        Condition::Reversed => return reverses(aheui),
+        Condition::Compare(value, cmp, rhs) => (value, cmp, rhs),
+    };
+    let lhs = match value {
+        Value::Step => aheui.step as i64,
+        Value::Len(sel) => aheui.stacks[sel.unwrap_or(aheui.sel)].len() as i64,
+        Value::Top(sel) => match top(aheui, sel.unwrap_or(aheui.sel)) {
+            Some(v) => v as i64,
+            None => return false,
+        },
+    };
+    match cmp {
+        Cmp::Lt => lhs < rhs,
+        Cmp::Le => lhs <= rhs,
+        Cmp::Eq => lhs == rhs,
+        Cmp::Ne => lhs != rhs,
+        Cmp::Ge => lhs >= rhs,
+        Cmp::Gt => lhs > rhs,
+    }
+}
+
+/// 지금 셀을 실행하기 전에 걸린 추적점의 메시지를 `log`로 보내고, 멈춰야 하면
+/// 걸린 첫 중단점의 번호. 멈춘 뒤 이어 갈 때는 한 걸음을 먼저 실행한다
+pub fn check(
+    aheui: &Aheui,
+    breakpoints: &[Breakpoint],
+    mut log: impl FnMut(String),
+) -> Option<usize> {
+    let mut stop = None;
+
+    for (idx, bp) in breakpoints.iter().enumerate() {
+        if !bp.hits(aheui) {
+            continue;
+        }
+        match bp.message(aheui) {
+            Some(message) => log(message),
+            None => {
+                stop.get_or_insert(idx);
+            }
+        }
+    }
+
+    stop
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (spec, log) = match s.split_once(" log ") {
+            Some((spec, log)) => (spec, Some(log.to_string())),
+            None => (s, None),
+        };
+        let mut words = spec.split_whitespace().peekable();
+        // 음수나 범위를 넘는 수는 자르지 않고 거부한다
+        fn number<T: FromStr>(word: Option<&str>) -> Result<T, String> {
+            let word = word.ok_or("expected a number")?;
+            word.parse()
+                .map_err(|_| format!("invalid number {:?}", word))
+        }
+        let coord = |word: &str| match number::<i32>(Some(word))? {
+            n if n >= 0 => Ok(n),
+            _ => Err(format!("invalid coordinate {:?}", word)),
+        };
+
+        let location = match words.next() {
+            Some("any") => Location::Any,
+            Some("op") => {
+                let word = words.next().ok_or("op needs a consonant or syllable")?;
+                let mut chars = word.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Location::Op(c),
+                    _ => return Err(format!("expected one character after op, got {:?}", word)),
+                }
+            }
+            Some("step") => Location::Step(number(words.next())?),
+            Some(cell) => {
+                let (x, y) = cell.split_once(',').ok_or_else(|| {
+                    format!("unknown location {:?}, expected x,y, op, step or any", cell)
+                })?;
+                Location::Cell(coord(x)?, coord(y)?)
+            }
+            None => return Err("empty breakpoint".to_string()),
+        };
+
+        let mut conditions = vec![];
+        match words.next() {
+            None => {}
+            Some("if") => loop {
+                let condition = match words.next() {
+                    Some("reversed") => Condition::Reversed,
+                    Some(word @ ("step" | "len" | "top")) => {
+                        let sel = match words.peek() {
+                            Some(w) if !CMPS.iter().any(|(_, s)| s == w) => {
+                                let w = words.next().unwrap();
+                                Some(storage(w).ok_or_else(|| format!("unknown storage {:?}", w))?)
+                            }
+                            _ => None,
+                        };
+                        let value = match (word, sel) {
+                            ("step", None) => Value::Step,
+                            ("step", Some(_)) => return Err("step takes no storage".to_string()),
+                            ("len", sel) => Value::Len(sel),
+                            (_, sel) => Value::Top(sel),
+                        };
+                        let op = words.next().ok_or("expected a comparison")?;
+                        let cmp = CMPS
+                            .iter()
+                            .find(|(_, s)| *s == op)
+                            .map(|&(c, _)| c)
+                            .ok_or_else(|| format!("unknown comparison {:?}", op))?;
+                        Condition::Compare(value, cmp, number(words.next())?)
+                    }
+                    word => {
+                        return Err(format!(
+                            "unknown condition {:?}, expected reversed, step, len or top",
+                            word.unwrap_or("")
+                        ));
+                    }
+                };
+                conditions.push(condition);
+                match words.next() {
+                    None => break,
+                    Some("and") => {}
+                    Some(word) => return Err(format!("expected and, got {:?}", word)),
+                }
+            },
+            Some("log") => return Err("log needs a message".to_string()),
+            Some(word) => return Err(format!("expected if or log, got {:?}", word)),
+        }
+
+        Ok(Breakpoint {
+            location,
+            conditions,
+            log,
+        })
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Location::Any => write!(f, "any")?,
+            Location::Cell(x, y) => write!(f, "{},{}", x, y)?,
+            Location::Op(c) => write!(f, "op {}", c)?,
+            Location::Step(n) => write!(f, "step {}", n)?,
+        }
+
+        for (idx, condition) in self.conditions.iter().enumerate() {
+            write!(f, " {} ", if idx == 0 { "if" } else { "and" })?;
+            match *condition {
+                Condition::Reversed => write!(f, "reversed")?,
+                Condition::Compare(value, cmp, rhs) => {
+                    let storage = |sel: Option<usize>| {
+                        sel.map(|idx| format!(" {}", storage_name(idx)))
+                            .unwrap_or_default()
+                    };
+                    match value {
+                        Value::Step => write!(f, "step")?,
+                        Value::Len(sel) => write!(f, "len{}", storage(sel))?,
+                        Value::Top(sel) => write!(f, "top{}", storage(sel))?,
+                    }
+                    let op = CMPS.iter().find(|(c, _)| *c == cmp).unwrap().1;
+                    write!(f, " {} {}", op, rhs)?;
+                }
+            }
+        }
+
+        if let Some(log) = &self.log {
+            write!(f, " log {}", log)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `steps`번 실행하는 동안 걸린 걸음들
+    fn hit_steps(src: &str, bp: &str, steps: usize) -> Vec<usize> {
+        let bp: Breakpoint = bp.parse().unwrap();
+        let mut aheui = Aheui::new(src.to_string());
+        let mut hits = vec![];
+        while !aheui.ended && aheui.step < steps {
+            if bp.hits(&aheui) {
+                hits.push(aheui.step);
+            }
+            aheui.next();
+        }
+        hits
+    }
+
+    #[test]
+    fn prints_what_it_parses() {
+        for text in [
+            "any",
+            "3,4",
+            "op ㅎ",
+            "op 망",
+            "step 100",
+            "any if reversed",
+            "0,0 if len 앗 > 100 and top == 0",
+            "op ㅁ if top 앙 != -1 and len <= 3",
+            "step 5 if step >= 2 and top < 7 log at {x},{y}",
+            "any log {step} {cell}",
+        ] {
+            let bp: Breakpoint = text.parse().unwrap();
+            assert_eq!(bp.to_string(), text);
+            assert_eq!(bp.to_string().parse::<Breakpoint>(), Ok(bp));
+        }
+    }
+
+    #[test]
+    fn rejects_numbers_it_cannot_hold() {
+        for text in [
+            "step -1",
+            "step 18446744073709551616",
+            "-1,0",
+            "0,-3",
+            "1,99999999999",
+            "1,2 if top == 99999999999999999999",
+        ] {
+            assert!(text.parse::<Breakpoint>().is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_breakpoints() {
+        for text in [
+            "",
+            "op",
+            "op 망희",
+            "step",
+            "1,2 log",
+            "1,2 unless top == 0",
+            "1,2 if top ~ 0",
+            "1,2 if step 앗 == 0",
+            "1,2 if top == 0 or len == 0",
+            "here",
+        ] {
+            assert!(text.parse::<Breakpoint>().is_err(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn hits_its_location_when_the_conditions_hold() {
+        let src = "반받다망희";
+        assert_eq!(hit_steps(src, "any", 10), [0, 1, 2, 3, 4]);
+        assert_eq!(hit_steps(src, "2,0", 10), [2]);
+        // 닿소리는 첫소리와 비교한다
+        assert_eq!(hit_steps(src, "op ㄷ", 10), [2]);
+        assert_eq!(hit_steps(src, "op 망", 10), [3]);
+        assert_eq!(hit_steps(src, "step 3 if top == 5", 10), [3]);
+        assert_eq!(hit_steps(src, "step 3 if top == 4", 10), [] as [usize; 0]);
+        assert_eq!(hit_steps(src, "any if len >= 2", 10), [2]);
+        // 빈 저장공간의 `top`은 무엇과도 같지 않다
+        assert_eq!(hit_steps(src, "any if top != 1", 10), [1, 2, 3]);
+    }
+
+    #[test]
+    fn sees_the_direction_turn_back() {
+        // 빈 스택에서 꺼내거나, 왼쪽으로 온 `아`이거나, 0이 아닌 값으로 `처`를
+        // 만나면 온 길로 돌아간다
+        assert_eq!(hit_steps("아마", "any if reversed", 4), [1, 2, 3]);
+        assert_eq!(hit_steps("아받처희", "any if reversed", 3), [2]);
+        // 0을 꺼낸 `ㅊ`은 반대로 가므로 오른쪽으로 지나간다
+        assert_eq!(
+            hit_steps("아바처희", "any if reversed", 4),
+            [] as [usize; 0]
+        );
+        // 끝내는 셀이나 빈 칸은 방향을 바꾸지 않는다
+        assert_eq!(hit_steps("ㅇ희", "any if reversed", 4), [] as [usize; 0]);
+    }
+}
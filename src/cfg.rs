@@ -0,0 +1,219 @@
+use std::str::FromStr;
+
+use crate::aheui::Aheui;
+use crate::disasm::{Exit, Run, dir_name, mnemonic, out_dir};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Graphviz
+    Dot,
+    /// 한 줄에 이동 하나
+    Text,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(Self::Dot),
+            "text" => Ok(Self::Text),
+            _ => Err(format!("unknown format {:?}, expected dot or text", s)),
+        }
+    }
+}
+
+/// 구간의 셀마다 한 줄. 이어지는 빈 동작은 하나로 줄인다
+fn cell_lines(aheui: &Aheui, run: &Run) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    let mut nops = 0;
+
+    for &((x, y), _) in &run.nodes {
+        let chr = aheui.src_map[y as usize][x as usize];
+        let op = mnemonic(chr);
+        if op == "nop" {
+            nops += 1;
+            continue;
+        }
+        if nops > 0 {
+            lines.push(format!("nop x{}", nops));
+            nops = 0;
+        }
+        lines.push(format!("({}, {}) {} {}", x, y, chr.3, op));
+    }
+    if nops > 0 {
+        lines.push(format!("nop x{}", nops));
+    }
+
+    lines
+}
+
+/// 이동의 이름. `ㅊ`은 참/거짓, 모자라 반사되면 reflect, 아니면 나가는 방향
+fn edge_label(aheui: &Aheui, run: &Run, exit: Exit) -> String {
+    let &((x, y), dir) = run.nodes.last().unwrap();
+    let chr = aheui.src_map[y as usize][x as usize];
+
+    match (exit, chr.0) {
+        (Exit::Next, 'ㅊ') => "nonzero".to_string(),
+        (Exit::Zero, _) => "zero".to_string(),
+        (Exit::Underflow, _) => "reflect".to_string(),
+        (Exit::Next, _) => dir_name(out_dir(chr, dir)),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// 구간을 노드로, 구간 사이의 이동을 간선으로
+pub fn render(aheui: &Aheui, runs: &[Run], format: Format) -> String {
+    let mut out = String::new();
+
+    match format {
+        Format::Dot => {
+            out += "digraph aheui {\n";
+            out += "    node [shape=box, fontname=monospace];\n";
+            out += "    start [shape=point];\n";
+            if !runs.is_empty() {
+                out += "    start -> L0;\n";
+            }
+            for run in runs {
+                let label: String = cell_lines(aheui, run)
+                    .iter()
+                    .map(|l| escape(l) + "\\l")
+                    .collect();
+                let ends = if run.exits.is_empty() {
+                    ", peripheries=2"
+                } else {
+                    ""
+                };
+                out += &format!(
+                    "    L{} [label=\"L{}:\\l{}\"{}];\n",
+                    run.label, run.label, label, ends
+                );
+            }
+            for run in runs {
+                for &(exit, to) in &run.exits {
+                    let style = match exit {
+                        Exit::Next => "",
+                        _ => ", style=dashed",
+                    };
+                    out += &format!(
+                        "    L{} -> L{} [label=\"{}\"{}];\n",
+                        run.label,
+                        to,
+                        edge_label(aheui, run, exit),
+                        style
+                    );
+                }
+            }
+            out += "}\n";
+        }
+        Format::Text => {
+            for run in runs {
+                out += &format!("L{}: {}\n", run.label, cell_lines(aheui, run).join("; "));
+                for &(exit, to) in &run.exits {
+                    out += &format!("    -> L{} [{}]\n", to, edge_label(aheui, run, exit));
+                }
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::disassemble;
+
+    fn dot(src: &str) -> String {
+        let aheui = Aheui::new(src.to_string());
+        render(&aheui, &disassemble(&aheui), Format::Dot)
+    }
+
+    /// 노드 이름과 간선 (시작, 끝, 이름표)
+    fn graph(dot: &str) -> (Vec<String>, Vec<(String, String, String)>) {
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        for line in dot.lines().map(str::trim) {
+            if let Some((from, rest)) = line.split_once(" -> ") {
+                let to = rest.split([' ', ';']).next().unwrap();
+                let label = rest
+                    .split_once("label=\"")
+                    .map_or("", |(_, l)| l.split('"').next().unwrap());
+                edges.push((from.to_string(), to.to_string(), label.to_string()));
+            } else if let Some((name, _)) = line.split_once(" [")
+                && name != "node"
+            {
+                nodes.push(name.to_string());
+            }
+        }
+        (nodes, edges)
+    }
+
+    fn edge(from: &str, to: &str, label: &str) -> (String, String, String) {
+        (from.to_string(), to.to_string(), label.to_string())
+    }
+
+    #[test]
+    fn exports_branches_and_joins() {
+        // `ㅈ` 뒤의 `ㅊ`에서 갈라지고 `망`에서 다시 만난다
+        let out = dot("방방주\nㅇㅇ추\n망희반\n");
+        let (nodes, edges) = graph(&out);
+        assert_eq!(nodes, ["start", "L0", "L1", "L2", "L3"]);
+        assert_eq!(
+            edges,
+            [
+                edge("start", "L0", ""),
+                edge("L0", "L1", "nonzero"),
+                edge("L0", "L3", "zero"),
+                edge("L1", "L2", "right"),
+                edge("L3", "L2", "right"),
+            ]
+        );
+        assert!(out.contains("    L0 -> L3 [label=\"zero\", style=dashed];\n"));
+        // 끝나는 구간만 두 겹
+        assert_eq!(out.matches("peripheries=2").count(), 1);
+        assert!(out.contains(
+            "L2 [label=\"L2:\\l(0, 2) 망 print num\\l(1, 2) 희 halt\\l\", peripheries=2];"
+        ));
+    }
+
+    #[test]
+    fn exports_two_exits_and_folds_nops() {
+        let out = dot("방추\nㅇ반망희\n번어히멍\n");
+        let (nodes, edges) = graph(&out);
+        assert_eq!(nodes, ["start", "L0", "L1", "L2"]);
+        assert_eq!(
+            edges,
+            [
+                edge("start", "L0", ""),
+                edge("L0", "L1", "nonzero"),
+                edge("L0", "L2", "zero"),
+            ]
+        );
+        assert_eq!(out.matches("peripheries=2").count(), 2);
+        assert!(out.contains("L2:\\lnop x1\\l(0, 2) 번 push 2\\l"));
+    }
+
+    #[test]
+    fn labels_reflections() {
+        let (_, edges) = graph(&dot("방뭉\n어뭉\n"));
+        assert_eq!(
+            edges,
+            [
+                edge("start", "L0", ""),
+                edge("L0", "L1", "reflect"),
+                edge("L1", "L1", "reflect"),
+            ]
+        );
+    }
+
+    #[test]
+    fn exports_only_the_start_for_an_empty_grid() {
+        let (nodes, edges) = graph(&dot(""));
+        assert_eq!(nodes, ["start"]);
+        assert!(edges.is_empty());
+    }
+}
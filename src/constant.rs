@@ -0,0 +1,381 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::OnceLock;
+
+use crate::hangul::assemble_jamo;
+use crate::normalize::canonical_count;
+
+/// 방향을 정하기 전의 명령 (닿소리, 받침)
+pub type Op = (char, (char, char));
+
+pub const ADD: Op = ('ㄷ', (' ', ' '));
+pub const MUL: Op = ('ㄸ', (' ', ' '));
+pub const SUB: Op = ('ㅌ', (' ', ' '));
+pub const DIV: Op = ('ㄴ', (' ', ' '));
+pub const DUP: Op = ('ㅃ', (' ', ' '));
+pub const SWAP: Op = ('ㅍ', (' ', ' '));
+
+/// `ㅂ`이 바로 쌓을 수 있는 수 (받침 획수)
+pub const PUSHABLE: [i64; 9] = [0, 2, 3, 4, 5, 6, 7, 8, 9];
+
+/// 획수가 `n`인 받침으로 쌓기
+pub fn push(n: i64) -> Op {
+    ('ㅂ', canonical_count(n as i32))
+}
+
+/// 모든 명령에 같은 홀소리를 붙인다
+pub fn render(ops: &[Op], vowel: char) -> String {
+    ops.iter()
+        .map(|&(start, end)| assemble_jamo(start, vowel, end))
+        .collect()
+}
+
+/// 명령마다의 비용.
+/// 한 줄로 이어진 명령은 칸마다 한 번씩 실행되므로 칸 수와 걸음 수가 같고,
+/// 기본값은 둘 다를 줄인다. 가중치를 바꾸면 특정 명령을 피하게 할 수 있다
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cost {
+    pub push: u32,
+    /// `ㄷ`, `ㄸ`, `ㅌ`, `ㄴ`
+    pub arith: u32,
+    pub dup: u32,
+    pub swap: u32,
+}
+
+impl Default for Cost {
+    fn default() -> Self {
+        Self {
+            push: 1,
+            arith: 1,
+            dup: 1,
+            swap: 1,
+        }
+    }
+}
+
+/// 값을 만드는 방법
+#[derive(Debug, Clone, Copy)]
+enum How {
+    Push,
+    /// 왼쪽을 먼저 쌓고 오른쪽을 쌓은 뒤 셈
+    Bin(Op, i64, i64),
+    /// `x ㅃ ㅃ op1 op2`: x op2 (x op1 x). `swap`이면 op2 전에 바꿔 (x op1 x) op2 x
+    Dup(Op, Option<Op>, bool, i64),
+    /// (q * k) op r. 곱은 표에 두지 않는다
+    Split(Op, (i64, i64), i64),
+    /// (s ㅃ ㄸ) op r
+    Square(Op, i64, i64),
+}
+
+/// 표에서 모든 조합을 찾는 값의 범위
+const TABLE: i64 = 512;
+/// 표 밖의 수를 나눠 볼 수
+const DIVISORS: i64 = 64;
+
+/// 정수 하나를 스택에 쌓는 가장 짧은(또는 그에 가까운) 명령 열을 찾는다.
+///
+/// |n| <= `TABLE`은 비용 순으로 모든 조합을 찾아 표에 두고, 그보다 큰 수는
+/// 작은 수로 나눈 몫과 나머지로 쪼개 찾은 결과를 기억한다.
+/// 명령은 스택에서 쓰는 것을 가정한다 (큐는 셈한 값을 앞에 넣는다)
+#[derive(Debug, Clone)]
+pub struct Synthesizer {
+    cost: Cost,
+    best: HashMap<i64, (u32, How)>,
+}
+
+impl Synthesizer {
+    pub fn new(cost: Cost) -> Self {
+        let mut synth = Self {
+            cost,
+            best: HashMap::new(),
+        };
+        synth.fill_table();
+        synth
+    }
+
+    /// 비용이 작은 값부터 확정하면서 이미 확정된 값과 묶어 본다
+    fn fill_table(&mut self) {
+        let cost = self.cost;
+        let mut heap = BinaryHeap::new();
+        let mut done: Vec<(i64, u32)> = vec![];
+
+        // 값마다 지금까지 가장 싼 후보. 힙에 남은 비싼 항목은 꺼낼 때 건너뛴다
+        let mut pending: HashMap<i64, (u32, How)> = HashMap::new();
+        for n in PUSHABLE {
+            pending.insert(n, (cost.push, How::Push));
+            heap.push(Reverse((cost.push, n)));
+        }
+
+        while let Some(Reverse((c, v))) = heap.pop() {
+            if self.best.contains_key(&v) || pending.get(&v).is_none_or(|&(pc, _)| pc != c) {
+                continue;
+            }
+            let h = pending[&v].1;
+            self.best.insert(v, (c, h));
+            done.push((v, c));
+
+            let mut candidates: Vec<(i64, u32, How)> = vec![];
+            for &(u, cu) in &done {
+                let both = c + cu + cost.arith;
+                for (l, r) in [(u, v), (v, u)] {
+                    candidates.push((l + r, both, How::Bin(ADD, l, r)));
+                    candidates.push((l * r, both, How::Bin(MUL, l, r)));
+                    candidates.push((l - r, both, How::Bin(SUB, l, r)));
+                    if r != 0 {
+                        candidates.push((l / r, both, How::Bin(DIV, l, r)));
+                    }
+                }
+            }
+
+            for op1 in [ADD, MUL] {
+                let x = apply(op1, v, v);
+                candidates.push((x, c + cost.dup + cost.arith, How::Dup(op1, None, false, v)));
+                for op2 in [ADD, MUL, SUB, DIV] {
+                    let both = c + 2 * cost.dup + 2 * cost.arith;
+                    if op2 == DIV && (x == 0 || v == 0) {
+                        continue;
+                    }
+                    candidates.push((apply(op2, v, x), both, How::Dup(op1, Some(op2), false, v)));
+                    if matches!(op2, SUB | DIV) {
+                        candidates.push((
+                            apply(op2, x, v),
+                            both + cost.swap,
+                            How::Dup(op1, Some(op2), true, v),
+                        ));
+                    }
+                }
+            }
+
+            for (n, nc, h) in candidates {
+                if n.abs() > TABLE || self.best.contains_key(&n) {
+                    continue;
+                }
+                if pending.get(&n).is_none_or(|&(pc, _)| nc < pc) {
+                    pending.insert(n, (nc, h));
+                    heap.push(Reverse((nc, n)));
+                }
+            }
+        }
+    }
+
+    /// `n`을 쌓는 비용
+    pub fn cost(&mut self, n: i32) -> u32 {
+        self.search(n as i64)
+    }
+
+    /// `n`을 쌓는 명령 열
+    pub fn synthesize(&mut self, n: i32) -> Vec<Op> {
+        let n = n as i64;
+        self.search(n);
+
+        let mut ops = vec![];
+        self.emit(n, &mut ops);
+        ops
+    }
+
+    fn search(&mut self, n: i64) -> u32 {
+        if let Some(&(c, _)) = self.best.get(&n) {
+            return c;
+        }
+
+        let cost = self.cost;
+        let mut found: Option<(u32, How)> = None;
+        let mut offer = |c: u32, h: How| {
+            if found.is_none_or(|(fc, _)| c < fc) {
+                found = Some((c, h));
+            }
+        };
+
+        if n < 0 && -n <= i32::MAX as i64 {
+            // 0에서 뺀다
+            let c = self.search(-n);
+            offer(cost.push + c + cost.arith, How::Bin(SUB, 0, -n));
+        }
+
+        // n = q * k + r, 또는 q를 하나 더 키우고 나머지를 뺀다
+        let sign = n.signum();
+        for k in 2..=DIVISORS {
+            let ck = self.search(k);
+            let (q, r) = (n / k, n % k);
+            for (q, r) in [(q, r), (q + sign, r - sign * k)] {
+                let prod = q * k;
+                if prod > i32::MAX as i64 || prod < i32::MIN as i64 {
+                    continue;
+                }
+                let c = self.search(q) + ck + cost.arith;
+                if r == 0 {
+                    offer(c, How::Bin(MUL, q, k));
+                } else {
+                    let (op, r) = if r > 0 { (ADD, r) } else { (SUB, -r) };
+                    let cr = self.search(r);
+                    offer(c + cr + cost.arith, How::Split(op, (q, k), r));
+                }
+            }
+        }
+
+        // 제곱에 가까운 수
+        if n > 0 {
+            let s = (n as f64).sqrt() as i64;
+            for s in [s, s + 1] {
+                let sq = s * s;
+                if sq > i32::MAX as i64 {
+                    continue;
+                }
+                let c = self.search(s) + cost.dup + cost.arith;
+                let r = n - sq;
+                if r == 0 {
+                    offer(c, How::Dup(MUL, None, false, s));
+                } else {
+                    let (op, r) = if r > 0 { (ADD, r) } else { (SUB, -r) };
+                    let cr = self.search(r);
+                    offer(c + cr + cost.arith, How::Square(op, s, r));
+                }
+            }
+        }
+
+        let (c, h) = found.expect("every integer can be split");
+        self.best.insert(n, (c, h));
+        c
+    }
+
+    fn emit(&self, n: i64, ops: &mut Vec<Op>) {
+        match self.best[&n].1 {
+            How::Push => ops.push(push(n)),
+            How::Bin(op, l, r) => {
+                self.emit(l, ops);
+                self.emit(r, ops);
+                ops.push(op);
+            }
+            How::Dup(op1, op2, swap, x) => {
+                self.emit(x, ops);
+                ops.push(DUP);
+                if op2.is_some() {
+                    ops.push(DUP);
+                }
+                ops.push(op1);
+                if let Some(op2) = op2 {
+                    if swap {
+                        ops.push(SWAP);
+                    }
+                    ops.push(op2);
+                }
+            }
+            How::Split(op, (q, k), r) => {
+                self.emit(q, ops);
+                self.emit(k, ops);
+                ops.push(MUL);
+                self.emit(r, ops);
+                ops.push(op);
+            }
+            How::Square(op, s, r) => {
+                self.emit(s, ops);
+                ops.extend([DUP, MUL]);
+                self.emit(r, ops);
+                ops.push(op);
+            }
+        }
+    }
+}
+
+impl Default for Synthesizer {
+    /// 기본 비용의 표는 한 번만 만들어 둔다
+    fn default() -> Self {
+        static DEFAULT: OnceLock<Synthesizer> = OnceLock::new();
+        DEFAULT.get_or_init(|| Self::new(Cost::default())).clone()
+    }
+}
+
+/// 스택의 둘째 값 `l`과 맨 위 값 `r`에 셈을 한 결과
+fn apply(op: Op, l: i64, r: i64) -> i64 {
+    match op {
+        ADD => l + r,
+        MUL => l * r,
+        SUB => l - r,
+        _ => l / r,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aheui::{Aheui, ExitCode};
+    use crate::equiv::run;
+
+    /// 명령을 한 줄로 실행하고 맨 위 값을 출력
+    fn value(ops: &[Op]) -> String {
+        let src = render(ops, 'ㅏ') + "망희";
+        let outcome = run(&Aheui::new(src), "", 100_000);
+        assert_eq!(outcome.exit, Some(ExitCode::Success(0)));
+        outcome.output
+    }
+
+    fn total(ops: &[Op], cost: Cost) -> u32 {
+        ops.iter()
+            .map(|&op| match op {
+                DUP => cost.dup,
+                SWAP => cost.swap,
+                ADD | MUL | SUB | DIV => cost.arith,
+                _ => cost.push,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn pushes_each_value() {
+        let mut synth = Synthesizer::default();
+        let values = [
+            0,
+            1,
+            -1,
+            2,
+            9,
+            10,
+            13,
+            97,
+            511,
+            512,
+            513,
+            7919,
+            65_536,
+            1_000_000,
+            720_720,
+            999_983,
+            -1000,
+            -65_537,
+            i32::MAX,
+            i32::MIN,
+        ];
+        for n in values {
+            let ops = synth.synthesize(n);
+            assert_eq!(value(&ops), n.to_string(), "{:?}", ops);
+            assert_eq!(total(&ops, Cost::default()), synth.cost(n), "{}", n);
+        }
+    }
+
+    #[test]
+    fn finds_the_shortest_small_values() {
+        let mut synth = Synthesizer::default();
+        assert_eq!(synth.synthesize(9), vec![push(9)]);
+        // 0에서 뺀다
+        assert_eq!(synth.synthesize(-2), vec![push(0), push(2), SUB]);
+        assert_eq!(synth.synthesize(81).len(), 3);
+    }
+
+    #[test]
+    fn follows_the_cost_weights() {
+        let mut default = Synthesizer::default();
+        let ops = default.synthesize(6561);
+        assert!(ops.contains(&DUP), "{:?}", ops);
+
+        let cost = Cost {
+            dup: 10,
+            ..Cost::default()
+        };
+        let mut avoiding = Synthesizer::new(cost);
+        let avoided = avoiding.synthesize(6561);
+        assert!(!avoided.contains(&DUP), "{:?}", avoided);
+        assert_ne!(ops, avoided);
+        assert_eq!(total(&avoided, cost), avoiding.cost(6561));
+        assert_eq!(value(&avoided), "6561");
+    }
+}
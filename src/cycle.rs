@@ -0,0 +1,236 @@
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// 같은 기계 상태가 다시 나타난 지점
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    /// 반복을 발견한 걸음
+    pub step: usize,
+    /// 같은 상태였던 이전 걸음
+    pub repeats: usize,
+    pub cursor: (i32, i32),
+    /// 저장공간이 자라기만 하는 루프
+    pub growing: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    cursor: (i32, i32),
+    dir: (i32, i32),
+    sel: usize,
+    stacks: [VecDeque<i32>; 27],
+}
+
+/// 자라기만 하는 루프를 찾기 위한 기준 상태
+#[derive(Debug, Clone)]
+struct Anchor {
+    step: usize,
+    cursor: (i32, i32),
+    dir: (i32, i32),
+    sel: usize,
+    depths: [usize; 27],
+    /// 기준 이후 기준 아래의 값을 읽거나 크기 검사에 실패하지 않았음
+    clean: bool,
+}
+
+/// 입출력 없이 같은 상태를 되풀이하는 실행을 찾는다.
+///
+/// 2의 거듭제곱 걸음마다 상태를 저장해 두고(Brent), `interval` 걸음마다 해시를 비교한다.
+/// 저장한 뒤 기준 깊이 아래를 건드리지 않고 같은 위치로 돌아오면 저장공간이
+/// 자라기만 하는 루프로 본다.
+#[derive(Debug, Clone)]
+pub struct LoopDetector {
+    interval: usize,
+    saved: Option<(usize, u64, Snapshot)>,
+    anchor: Option<Anchor>,
+    next_save: usize,
+}
+
+impl LoopDetector {
+    pub fn new(interval: usize) -> Self {
+        Self {
+            interval: interval.max(1),
+            saved: None,
+            anchor: None,
+            next_save: 1,
+        }
+    }
+
+    /// 같은 간격으로 처음부터
+    pub fn reset(&mut self) {
+        *self = Self::new(self.interval);
+    }
+
+    /// 값을 꺼낸 뒤 남은 크기
+    pub fn on_pop(&mut self, sel: usize, len: usize) {
+        if let Some(anchor) = &mut self.anchor {
+            // 대기열은 앞에서 꺼내므로 기준 이전의 값을 읽게 된다
+            if sel == 21 || len < anchor.depths[sel] {
+                anchor.clean = false;
+            }
+        }
+    }
+
+    /// 크기가 모자라 반사됨. 저장공간이 자라면 결과가 달라질 수 있다
+    pub fn on_check_failed(&mut self) {
+        if let Some(anchor) = &mut self.anchor {
+            anchor.clean = false;
+        }
+    }
+
+    /// 입출력이 있으면 이전 상태와 비교하지 않는다
+    pub fn on_io(&mut self, step: usize) {
+        self.saved = None;
+        self.anchor = None;
+        self.next_save = step + 1;
+    }
+
+    pub fn observe(
+        &mut self,
+        step: usize,
+        cursor: (i32, i32),
+        dir: (i32, i32),
+        sel: usize,
+        stacks: &[VecDeque<i32>; 27],
+    ) -> Option<Cycle> {
+        if let Some(anchor) = &self.anchor
+            && anchor.clean
+            && (anchor.cursor, anchor.dir, anchor.sel) == (cursor, dir, sel)
+            && anchor.depths.iter().zip(stacks).all(|(&d, s)| s.len() >= d)
+        {
+            return Some(Cycle {
+                step,
+                repeats: anchor.step,
+                cursor,
+                growing: anchor.depths.iter().zip(stacks).any(|(&d, s)| s.len() > d),
+            });
+        }
+
+        if step >= self.next_save {
+            let snapshot = Snapshot {
+                cursor,
+                dir,
+                sel,
+                stacks: stacks.clone(),
+            };
+            self.saved = Some((step, hash(cursor, dir, sel, stacks), snapshot));
+            self.anchor = Some(Anchor {
+                step,
+                cursor,
+                dir,
+                sel,
+                depths: std::array::from_fn(|i| stacks[i].len()),
+                clean: true,
+            });
+            self.next_save = step * 2;
+            return None;
+        }
+
+        if !step.is_multiple_of(self.interval) {
+            return None;
+        }
+
+        let (saved_step, saved_hash, saved) = self.saved.as_ref()?;
+        if (saved.cursor, saved.dir, saved.sel) != (cursor, dir, sel) {
+            return None;
+        }
+
+        if hash(cursor, dir, sel, stacks) == *saved_hash && saved.stacks == *stacks {
+            return Some(Cycle {
+                step,
+                repeats: *saved_step,
+                cursor,
+                growing: false,
+            });
+        }
+
+        None
+    }
+}
+
+fn hash(cursor: (i32, i32), dir: (i32, i32), sel: usize, stacks: &[VecDeque<i32>; 27]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (cursor, dir, sel, stacks).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aheui::{Aheui, ExitCode};
+
+    fn run(src: &str, interval: usize, steps: usize) -> Aheui {
+        let mut aheui = Aheui::new(src.to_string());
+        aheui.debug(true);
+        aheui.detect_loops(Some(interval));
+        while !aheui.ended && aheui.step < steps {
+            aheui.next();
+        }
+        aheui
+    }
+
+    fn cycle(src: &str, interval: usize) -> Cycle {
+        match run(src, interval, 100_000).exit_code {
+            Some(ExitCode::NonTerminating(cycle)) => cycle,
+            other => panic!("{:?} instead of a cycle", other),
+        }
+    }
+
+    type State = ((i32, i32), (i32, i32), usize, [VecDeque<i32>; 27]);
+
+    /// `step`번 실행한 뒤의 상태
+    fn state_at(src: &str, step: usize) -> State {
+        let aheui = run(src, usize::MAX, step);
+        (aheui.cursor, aheui.dir, aheui.sel, aheui.stacks)
+    }
+
+    #[test]
+    fn reports_the_steps_of_a_repeated_state() {
+        // 넣고 꺼내기를 되풀이해 세 걸음마다 같은 상태가 된다
+        let src = "아바마\n";
+        for interval in [1, 2, 5] {
+            let cycle = cycle(src, interval);
+            assert!(!cycle.growing);
+            assert!(cycle.repeats < cycle.step);
+            assert_eq!((cycle.step - cycle.repeats) % 3, 0);
+            assert_eq!(state_at(src, cycle.step), state_at(src, cycle.repeats));
+            assert_eq!(state_at(src, cycle.step).0, cycle.cursor);
+        }
+        assert_eq!(
+            cycle(src, 1),
+            Cycle {
+                step: 7,
+                repeats: 4,
+                cursor: (1, 0),
+                growing: false
+            }
+        );
+    }
+
+    #[test]
+    fn reports_a_loop_that_only_pushes() {
+        let cycle = cycle("아바\n", 1000);
+        assert!(cycle.growing);
+        assert_eq!(state_at("아바\n", cycle.step).0, cycle.cursor);
+        assert!(cycle.step < 1000);
+    }
+
+    #[test]
+    fn lets_a_long_countdown_finish() {
+        // 4802에서 2씩 빼며 0이 될 때까지 돈다
+        let src = "붉ㅇㅇ\n붉ㅇㅇ\n뚜ㅇㅇ\n뿌ㅇㅇ\n뚜ㅇㅇ\n분ㅇㅇ\n뚜ㅇ희\n아빠추\nㅇㅇ분\nㅇㅇ투\nㅇ오어\n";
+        for interval in [1, 7, 64] {
+            let aheui = run(src, interval, 1_000_000);
+            assert_eq!(aheui.exit_code, Some(ExitCode::Success(0)));
+            assert!(aheui.step > 10_000);
+        }
+    }
+
+    #[test]
+    fn forgets_states_across_output() {
+        // 출력이 있으면 같은 위치와 저장공간으로 돌아와도 반복이 아니다
+        let aheui = run("바빠망\n", 1, 1000);
+        assert_eq!(aheui.exit_code, None);
+        assert_eq!(aheui.step, 1000);
+    }
+}
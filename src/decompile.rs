@@ -0,0 +1,745 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::aheui::Aheui;
+use crate::analysis::{DepthAnalysis, required_depth};
+use crate::disasm::{Exit, Node, Run, disassemble, out_dir};
+use crate::hangul::{KChar, count_lines_in_char, get_end_count, storage_name};
+
+/// 대기열(ㅇ)의 번호
+const QUEUE: usize = 21;
+
+/// 구간 안에서 아직 저장공간에 넣지 않은 값
+#[derive(Debug, Clone)]
+enum Expr {
+    Const(i32),
+    Temp(usize),
+    /// 연산자와 (둘째 값, 맨 위 값)
+    Bin(&'static str, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn prec(&self) -> u8 {
+        match self {
+            Expr::Bin(">=" | "<", ..) => 1,
+            Expr::Bin("+" | "-", ..) => 2,
+            Expr::Bin(..) => 3,
+            _ => 4,
+        }
+    }
+
+    fn render(&self) -> String {
+        match self {
+            Expr::Const(n) => n.to_string(),
+            Expr::Temp(t) => format!("t{}", t),
+            Expr::Bin(op, l, r) => {
+                let side = |e: &Expr, right: bool| {
+                    let inner = e.render();
+                    if e.prec() < self.prec() || (right && e.prec() == self.prec()) {
+                        format!("({})", inner)
+                    } else {
+                        inner
+                    }
+                };
+                format!("{} {} {}", side(l, false), op, side(r, true))
+            }
+        }
+    }
+
+    /// 0이 아닐 때 참인 조건과 그 반대
+    fn conditions(&self) -> (String, String) {
+        match self {
+            Expr::Bin(">=", l, r) => (self.render(), Expr::Bin("<", l.clone(), r.clone()).render()),
+            _ => (
+                format!("{} != 0", self.render()),
+                format!("{} == 0", self.render()),
+            ),
+        }
+    }
+
+    fn trivial(&self) -> bool {
+        matches!(self, Expr::Const(_) | Expr::Temp(_))
+    }
+}
+
+/// 두 값이 상수면 미리 계산한다. 0으로 나누면 프로그램이 끝나므로 남긴다
+fn bin(op: &'static str, l: Expr, r: Expr) -> Expr {
+    if let (Expr::Const(a), Expr::Const(b)) = (&l, &r) {
+        let (a, b) = (*a, *b);
+        let folded = match op {
+            "+" => Some(a.wrapping_add(b)),
+            "*" => Some(a.wrapping_mul(b)),
+            "-" => Some(a.wrapping_sub(b)),
+            ">=" => Some((a >= b) as i32),
+            "/" if b != 0 => Some(a.wrapping_div(b)),
+            "%" if b != 0 => Some(a.wrapping_rem(b)),
+            _ => None,
+        };
+        if let Some(n) = folded {
+            return Expr::Const(n);
+        }
+    }
+    Expr::Bin(op, Box::new(l), Box::new(r))
+}
+
+/// 저장공간. 구간 시작에서 선택이 하나로 정해지지 않으면 `None`(cur)
+type Place = Option<usize>;
+
+fn place_name(place: Place) -> String {
+    place.map_or("cur".to_string(), |idx| storage_name(idx).to_string())
+}
+
+/// 구간이 끝나고 가는 곳
+#[derive(Debug, Clone)]
+enum Term {
+    End,
+    Goto(usize),
+    /// 조건(과 그 반대)이 참이면 `then_stmts`를 하고 `then`으로
+    Branch {
+        cond: (String, String),
+        then_stmts: Vec<String>,
+        then: usize,
+        other: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Block {
+    stmts: Vec<String>,
+    term: Term,
+}
+
+/// 구간 하나를 문장으로 옮기는 상태
+struct Translator<'a> {
+    temps: &'a mut usize,
+    /// 선택이 바뀌는 곳을 보여야 하는지
+    dynamic: bool,
+    sel: Place,
+    /// 스택마다 아직 넣지 않은 값. 끝낼 수 있는 셈은 두지 않는다
+    pending: HashMap<usize, Vec<Expr>>,
+    stmts: Vec<String>,
+}
+
+impl Translator<'_> {
+    fn temp(&mut self, value: String) -> Expr {
+        *self.temps += 1;
+        self.stmts.push(format!("let t{} = {}", self.temps, value));
+        Expr::Temp(*self.temps)
+    }
+
+    fn pop(&mut self) -> Expr {
+        match self.sel {
+            Some(idx) => {
+                if let Some(e) = self.pending.get_mut(&idx).and_then(|p| p.pop()) {
+                    return e;
+                }
+            }
+            // 선택된 저장공간에 넣지 않은 값이 있을 수 있다
+            None => self.flush(),
+        }
+        self.temp(format!("{}.pop()", place_name(self.sel)))
+    }
+
+    /// 출력, 입력, 끝내기처럼 순서가 드러나는 문장. 그 앞의 값은 먼저 넣는다
+    fn effect(&mut self, stmt: String) {
+        self.flush();
+        self.stmts.push(stmt);
+    }
+
+    /// 대기열은 `ㅂ`, `ㅈ`, `ㅆ`로 넣으면 뒤, 셈 결과는 앞에 넣는다
+    fn push(&mut self, place: Place, e: Expr, front: bool) {
+        match place {
+            Some(idx) if idx != QUEUE => self.pending.entry(idx).or_default().push(e),
+            _ => {
+                let method = if front && place == Some(QUEUE) {
+                    "push_front"
+                } else {
+                    "push"
+                };
+                self.effect(format!("{}.{}({})", place_name(place), method, e.render()));
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        let mut idxs: Vec<usize> = self.pending.keys().copied().collect();
+        idxs.sort();
+        for idx in idxs {
+            for e in self.pending.remove(&idx).unwrap_or_default() {
+                self.stmts
+                    .push(format!("{}.push({})", storage_name(idx), e.render()));
+            }
+        }
+    }
+
+    /// 셀 하나. `reflected`면 값이 모자라 아무 일도 하지 않았다
+    fn op(&mut self, chr: KChar, reflected: bool) {
+        if chr.0 == ' ' || (reflected && chr.0 != 'ㅊ') {
+            return;
+        }
+
+        let sel = self.sel;
+        match chr.0 {
+            'ㄷ' | 'ㄸ' | 'ㅌ' | 'ㄴ' | 'ㄹ' | 'ㅈ' => {
+                let a = self.pop();
+                let b = self.pop();
+                let op = match chr.0 {
+                    'ㄷ' => "+",
+                    'ㄸ' => "*",
+                    'ㅌ' => "-",
+                    'ㄴ' => "/",
+                    'ㄹ' => "%",
+                    _ => ">=",
+                };
+                let mut e = bin(op, b, a);
+                // 0으로 나누면 끝나므로 미루지 않는다
+                if let Expr::Bin("/" | "%", ..) = e {
+                    self.flush();
+                    e = self.temp(e.render());
+                }
+                self.push(sel, e, chr.0 != 'ㅈ');
+            }
+            'ㅁ' => {
+                let e = self.pop();
+                match chr.2 {
+                    ('ㅇ', ' ') => self.effect(format!("print({})", e.render())),
+                    ('ㅎ', ' ') => {
+                        let shown = match e {
+                            Expr::Const(n) => char::from_u32(n as u32)
+                                .filter(|c| !c.is_control())
+                                .map_or(e.render(), |c| format!("{:?}", c)),
+                            _ => e.render(),
+                        };
+                        self.effect(format!("print_char({})", shown));
+                    }
+                    _ => {}
+                }
+            }
+            'ㅂ' => match chr.2 {
+                ('ㅇ', ' ') => {
+                    self.flush();
+                    let e = self.temp("read_num()".to_string());
+                    self.push(sel, e, false);
+                }
+                ('ㅎ', ' ') => self.effect(format!("{}.push_line(read_line())", place_name(sel))),
+                end => self.push(sel, Expr::Const(count_lines_in_char(end)), false),
+            },
+            'ㅃ' => {
+                let mut a = self.pop();
+                if !a.trivial() {
+                    a = self.temp(a.render());
+                }
+                self.push(sel, a.clone(), true);
+                self.push(sel, a, true);
+            }
+            'ㅍ' => {
+                let a = self.pop();
+                let b = self.pop();
+                self.push(sel, a, true);
+                self.push(sel, b, true);
+            }
+            'ㅅ' => {
+                let to = get_end_count(chr.2);
+                self.sel = Some(to);
+                if self.dynamic {
+                    self.stmts.push(format!("select({})", storage_name(to)));
+                }
+            }
+            'ㅆ' => {
+                let a = self.pop();
+                self.push(Some(get_end_count(chr.2)), a, false);
+            }
+            'ㅊ' => {
+                self.pop();
+            }
+            'ㅎ' => {
+                let e = self.pop();
+                self.effect(format!("exit({})", e.render()));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 모든 진입점에서 `entry`까지의 직접 지배자. 닿지 않는 곳은 `None`
+fn dominators(succs: &[Vec<usize>], entry: usize) -> Vec<Option<usize>> {
+    let n = succs.len();
+    let mut order = vec![];
+    let mut seen = vec![false; n];
+    let mut stack = vec![(entry, 0)];
+    seen[entry] = true;
+    while let Some((b, i)) = stack.pop() {
+        if i < succs[b].len() {
+            stack.push((b, i + 1));
+            let s = succs[b][i];
+            if !seen[s] {
+                seen[s] = true;
+                stack.push((s, 0));
+            }
+        } else {
+            order.push(b);
+        }
+    }
+    order.reverse();
+
+    let mut rpo = vec![usize::MAX; n];
+    for (i, &b) in order.iter().enumerate() {
+        rpo[b] = i;
+    }
+    let mut preds = vec![vec![]; n];
+    for (b, next) in succs.iter().enumerate() {
+        for &s in next {
+            preds[s].push(b);
+        }
+    }
+
+    let mut idom: Vec<Option<usize>> = vec![None; n];
+    idom[entry] = Some(entry);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &b in order.iter().skip(1) {
+            let mut new: Option<usize> = None;
+            for &p in &preds[b] {
+                if idom[p].is_none() {
+                    continue;
+                }
+                new = Some(match new {
+                    None => p,
+                    Some(mut a) => {
+                        let mut p = p;
+                        while a != p {
+                            while rpo[a] > rpo[p] {
+                                a = idom[a].unwrap();
+                            }
+                            while rpo[p] > rpo[a] {
+                                p = idom[p].unwrap();
+                            }
+                        }
+                        a
+                    }
+                });
+            }
+            if new != idom[b] {
+                idom[b] = new;
+                changed = true;
+            }
+        }
+    }
+
+    idom
+}
+
+fn dominates(idom: &[Option<usize>], a: usize, mut b: usize) -> bool {
+    loop {
+        if a == b {
+            return true;
+        }
+        match idom[b] {
+            Some(p) if p != b => b = p,
+            _ => return false,
+        }
+    }
+}
+
+/// 구간들을 `loop`와 `if`/`else`로 묶어 적는 상태
+struct Emitter<'a> {
+    blocks: &'a [Block],
+    /// 반복의 머리마다 빠져나가는 곳
+    follows: HashMap<usize, Option<usize>>,
+    /// 구간마다 모든 길이 다시 만나는 곳
+    merges: Vec<Option<usize>>,
+    gotos: HashSet<usize>,
+    emitted: HashSet<usize>,
+    lines: Vec<String>,
+}
+
+impl Emitter<'_> {
+    fn line(&mut self, depth: usize, text: &str) {
+        self.lines.push(format!("{}{}", "    ".repeat(depth), text));
+    }
+
+    fn goto(&mut self, b: usize, depth: usize) {
+        self.gotos.insert(b);
+        self.line(depth, &format!("goto L{}", b));
+    }
+
+    /// `b`부터 `stop` 직전까지. `loops`는 바깥부터 (머리, 빠져나가는 곳)
+    fn emit(
+        &mut self,
+        mut b: usize,
+        stop: Option<usize>,
+        loops: &[(usize, Option<usize>)],
+        depth: usize,
+    ) {
+        loop {
+            if Some(b) == stop {
+                return;
+            }
+            if let Some(&(head, follow)) = loops.last() {
+                if b == head {
+                    return self.line(depth, "continue");
+                }
+                if Some(b) == follow {
+                    return self.line(depth, "break");
+                }
+            }
+            if self.emitted.contains(&b) {
+                return self.goto(b, depth);
+            }
+
+            self.emitted.insert(b);
+            let next = match self.follows.get(&b) {
+                Some(&follow) => {
+                    self.line(depth, "loop {");
+                    let inner = [loops, &[(b, follow)]].concat();
+                    if let Some(next) = self.body(b, &inner, depth + 1) {
+                        self.emit(next, None, &inner, depth + 1);
+                    }
+                    // 몸의 끝은 저절로 처음으로 돌아간다
+                    if self.lines.last().is_some_and(|l| l.trim() == "continue") {
+                        self.lines.pop();
+                    }
+                    self.line(depth, "}");
+                    follow
+                }
+                None => self.body(b, loops, depth),
+            };
+
+            match next {
+                Some(next) => b = next,
+                None => return,
+            }
+        }
+    }
+
+    /// 구간 하나의 문장과 갈림. 이어서 적을 구간을 돌려준다
+    fn body(&mut self, b: usize, loops: &[(usize, Option<usize>)], depth: usize) -> Option<usize> {
+        if self.gotos.contains(&b) {
+            self.lines.push(format!("L{}:", b));
+        }
+        let block = &self.blocks[b];
+        for stmt in &block.stmts {
+            self.line(depth, stmt);
+        }
+
+        match &block.term {
+            Term::End => None,
+            Term::Goto(next) => Some(*next),
+            Term::Branch {
+                cond,
+                then_stmts,
+                then,
+                other,
+            } => {
+                let merge = self.merges[b];
+
+                let start = self.lines.len();
+                for stmt in then_stmts {
+                    self.line(depth + 1, stmt);
+                }
+                self.emit(*then, merge, loops, depth + 1);
+                let then_lines = self.lines.split_off(start);
+                self.emit(*other, merge, loops, depth + 1);
+                let other_lines = self.lines.split_off(start);
+
+                if then_lines.is_empty() {
+                    self.line(depth, &format!("if {} {{", cond.1));
+                    self.lines.extend(other_lines);
+                } else {
+                    self.line(depth, &format!("if {} {{", cond.0));
+                    self.lines.extend(then_lines);
+                    if !other_lines.is_empty() {
+                        self.line(depth, "} else {");
+                        self.lines.extend(other_lines);
+                    }
+                }
+                self.line(depth, "}");
+
+                merge
+            }
+        }
+    }
+}
+
+/// 도달할 수 있는 흐름을 `loop`, `if`/`else`와 저장공간 이름으로 된 의사 코드로.
+/// 나뉘지 않는 흐름은 `goto`로 남긴다
+pub fn decompile(aheui: &Aheui) -> String {
+    let runs = disassemble(aheui);
+    if runs.is_empty() {
+        return String::new();
+    }
+
+    let analysis = DepthAnalysis::run(aheui);
+    let mut sels: HashMap<Node, HashSet<usize>> = HashMap::new();
+    for state in analysis.states.keys() {
+        sels.entry((state.cursor, state.dir))
+            .or_default()
+            .insert(state.sel);
+    }
+    let start_sel = |run: &Run| -> Place {
+        let set = &sels[&run.nodes[0]];
+        (set.len() == 1).then(|| *set.iter().next().unwrap())
+    };
+    let dynamic = runs.iter().any(|run| start_sel(run).is_none());
+
+    let cell = |(x, y): (i32, i32)| aheui.src_map[y as usize][x as usize];
+    let mut temps = 0;
+    let blocks: Vec<Block> = runs
+        .iter()
+        .map(|run| {
+            let mut tr = Translator {
+                temps: &mut temps,
+                dynamic,
+                sel: start_sel(run),
+                pending: HashMap::new(),
+                stmts: vec![],
+            };
+
+            let (last, init) = run.nodes.split_last().unwrap();
+            for (i, &(cursor, dir)) in init.iter().enumerate() {
+                let chr = cell(cursor);
+                let reflected = run.nodes[i + 1].1 != out_dir(chr, dir);
+                tr.op(chr, reflected);
+            }
+
+            let chr = cell(last.0);
+            let term = match run.exits.as_slice() {
+                [] => {
+                    tr.op(chr, false);
+                    Term::End
+                }
+                [(exit, next)] => {
+                    tr.op(chr, *exit != Exit::Next);
+                    Term::Goto(*next)
+                }
+                [(_, then), (_, other), ..] if chr.0 == 'ㅊ' => {
+                    let e = tr.pop();
+                    tr.flush();
+                    return Block {
+                        stmts: tr.stmts,
+                        term: Term::Branch {
+                            cond: e.conditions(),
+                            then_stmts: vec![],
+                            then: *then,
+                            other: *other,
+                        },
+                    };
+                }
+                [(_, then), (_, other), ..] => {
+                    tr.flush();
+                    let (name, count) = (place_name(tr.sel), required_depth(&chr).unwrap_or(0));
+                    let cond = (
+                        format!("{}.len() >= {}", name, count),
+                        format!("{}.len() < {}", name, count),
+                    );
+                    let stmts = std::mem::take(&mut tr.stmts);
+                    tr.op(chr, false);
+                    tr.flush();
+                    return Block {
+                        stmts,
+                        term: Term::Branch {
+                            cond,
+                            then_stmts: tr.stmts,
+                            then: *then,
+                            other: *other,
+                        },
+                    };
+                }
+            };
+            tr.flush();
+
+            Block {
+                stmts: tr.stmts,
+                term,
+            }
+        })
+        .collect();
+
+    let succs: Vec<Vec<usize>> = blocks
+        .iter()
+        .map(|b| match b.term {
+            Term::End => vec![],
+            Term::Goto(n) => vec![n],
+            Term::Branch { then, other, .. } => vec![then, other],
+        })
+        .collect();
+
+    // 끝나는 구간을 모두 잇는 가상의 출구에서 거꾸로 지배자를 구하면 다시 만나는 곳
+    let n = blocks.len();
+    let mut reversed = vec![vec![]; n + 1];
+    for (b, next) in succs.iter().enumerate() {
+        if next.is_empty() {
+            reversed[n].push(b);
+        }
+        for &s in next {
+            reversed[s].push(b);
+        }
+    }
+    let merges: Vec<Option<usize>> = dominators(&reversed, n)
+        .into_iter()
+        .take(n)
+        .map(|m| m.filter(|&m| m != n))
+        .collect();
+
+    // 지배자로 돌아가는 이동이 반복
+    let idom = dominators(&succs, 0);
+    let mut follows: HashMap<usize, Option<usize>> = HashMap::new();
+    for (b, next) in succs.iter().enumerate() {
+        for &head in next {
+            if idom[b].is_none() || !dominates(&idom, head, b) {
+                continue;
+            }
+            // 머리로 돌아오는 모든 구간
+            let mut body: HashSet<usize> = HashSet::from([head]);
+            let mut stack = vec![b];
+            while let Some(x) = stack.pop() {
+                if body.insert(x) {
+                    for (p, ps) in succs.iter().enumerate() {
+                        if ps.contains(&x) {
+                            stack.push(p);
+                        }
+                    }
+                }
+            }
+            let follow = body
+                .iter()
+                .flat_map(|&x| succs[x].iter().copied())
+                .filter(|s| !body.contains(s))
+                .min();
+            follows.entry(head).or_insert(follow);
+        }
+    }
+
+    let mut emitter = Emitter {
+        blocks: &blocks,
+        follows,
+        merges,
+        gotos: HashSet::new(),
+        emitted: HashSet::new(),
+        lines: vec![],
+    };
+    // 처음에는 goto가 가는 곳을 모으고, 다시 적을 때 이름표를 붙인다
+    emitter.emit(0, None, &[], 0);
+    emitter.lines.clear();
+    emitter.emitted.clear();
+    emitter.emit(0, None, &[], 0);
+
+    let mut out = String::from("// pop() on an empty storage gives 0, 앙 is the queue\n");
+    for line in emitter.lines {
+        out += &line;
+        out += "\n";
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(src: &str) -> Vec<String> {
+        decompile(&Aheui::new(src.to_string()))
+            .lines()
+            .skip(1)
+            .map(|line| line.trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn divides_before_later_prints() {
+        assert_eq!(
+            lines("방받나밥망희\n"),
+            [
+                "let t1 = read_num()",
+                "let t2 = t1 / 3",
+                "아.push(t2)",
+                "print(4)",
+                "let t3 = 아.pop()",
+                "exit(t3)",
+            ]
+        );
+    }
+
+    #[test]
+    fn folds_values_that_cannot_trap() {
+        assert_eq!(
+            lines("바받나받다망희\n"),
+            ["print(3)", "let t1 = 아.pop()", "exit(t1)"]
+        );
+    }
+
+    #[test]
+    fn pushes_pending_values_before_popping_a_dynamic_storage() {
+        let lines = lines("방추\nㅇ순\nㅇ바바싸망희\n");
+        let tail: Vec<&str> = lines
+            .iter()
+            .rev()
+            .take(7)
+            .rev()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            tail,
+            [
+                "cur.push(0)",
+                "let t2 = cur.pop()",
+                "아.push(t2)",
+                "let t3 = cur.pop()",
+                "print(t3)",
+                "let t4 = cur.pop()",
+                "exit(t4)",
+            ]
+        );
+    }
+
+    #[test]
+    fn structures_a_counting_loop() {
+        let out = decompile(&Aheui::new(
+            include_str!("../tests/asm/countdown.aheui").to_string(),
+        ));
+        assert_eq!(
+            out.lines().skip(1).collect::<Vec<_>>(),
+            [
+                "아.push(10)",
+                "loop {",
+                "    let t1 = 아.pop()",
+                "    아.push(t1)",
+                "    print(t1)",
+                "    print_char(10)",
+                "    let t2 = 아.pop()",
+                "    let t3 = t2 - 1",
+                "    아.push(t3)",
+                "    if t3 != 0 {",
+                "        continue",
+                "    }",
+                "    break",
+                "}",
+                "let t4 = 아.pop()",
+                "exit(t4)",
+            ]
+        );
+    }
+
+    #[test]
+    fn structures_both_arms_of_a_branch() {
+        // input num; brz zero; push 1; print num; jmp end
+        // zero: push 2; print num; end: push 10; print char; halt
+        let src = "아우ㅇㅇ\nㅇ붕ㅇㅇ\n우처우ㅇ\n아우ㅇㅇ\nㅇ북ㅇㅇ\nㅇ북ㅇㅇ\n\
+                   ㅇ누ㅇㅇ\nㅇ뭉ㅇㅇ\nㅇ아ㅇ우\nㅇ우어ㅇ\nㅇ북ㅇㅇ\nㅇ뭉ㅇㅇ\n\
+                   ㅇ우ㅇ어\nㅇ북ㅇㅇ\nㅇ불ㅇㅇ\nㅇ뚜ㅇㅇ\nㅇ뭏ㅇㅇ\nㅇ후ㅇㅇ\n";
+        let out = decompile(&Aheui::new(src.to_string()));
+        assert_eq!(
+            out.lines().skip(1).collect::<Vec<_>>(),
+            [
+                "let t1 = read_num()",
+                "if t1 != 0 {",
+                "    print(1)",
+                "} else {",
+                "    print(2)",
+                "}",
+                "print_char(10)",
+                "let t2 = 아.pop()",
+                "exit(t2)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::aheui::{Aheui, turn};
+use crate::analysis::DepthAnalysis;
+use crate::hangul::{KChar, count_lines_in_char, get_end_count, storage_name};
+
+/// 셀과 그 셀에 들어온 방향. 방향에 따라 나가는 곳이 달라지므로 따로 센다
+pub type Node = ((i32, i32), (i32, i32));
+
+/// 한 줄로 이어진 실행이 끝나고 넘어가는 까닭
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// 보통 방향
+    Next,
+    /// `ㅊ`에서 꺼낸 값이 0이거나 꺼낼 값이 없어 반사
+    Zero,
+    /// 저장공간에 값이 모자라 반사
+    Underflow,
+}
+
+/// 중간에 갈라지거나 합쳐지지 않는 실행 구간
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub label: usize,
+    pub nodes: Vec<Node>,
+    /// (까닭, 이어지는 구간의 `label`). 비었으면 끝난다
+    pub exits: Vec<(Exit, usize)>,
+}
+
+/// 셀이 하는 일. 어셈블러와 같은 이름을 쓴다
+pub fn mnemonic(chr: KChar) -> String {
+    let storage = || storage_name(get_end_count(chr.2));
+
+    match chr.0 {
+        ' ' | 'ㅇ' | 'ㄱ' | 'ㄲ' => "nop".to_string(),
+        'ㅎ' => "halt".to_string(),
+        'ㄷ' => "add".to_string(),
+        'ㄸ' => "mul".to_string(),
+        'ㅌ' => "sub".to_string(),
+        'ㄴ' => "div".to_string(),
+        'ㄹ' => "mod".to_string(),
+        'ㅃ' => "dup".to_string(),
+        'ㅍ' => "swap".to_string(),
+        'ㅈ' => "cmp".to_string(),
+        'ㅊ' => "brz".to_string(),
+        'ㅁ' => match chr.2 {
+            ('ㅇ', ' ') => "print num".to_string(),
+            ('ㅎ', ' ') => "print char".to_string(),
+            _ => "pop".to_string(),
+        },
+        'ㅂ' => match chr.2 {
+            ('ㅇ', ' ') => "input num".to_string(),
+            ('ㅎ', ' ') => "input char".to_string(),
+            end => format!("push {}", count_lines_in_char(end)),
+        },
+        'ㅅ' => format!("sel {}", storage()),
+        'ㅆ' => format!("move {}", storage()),
+        _ => "reflect".to_string(),
+    }
+}
+
+/// 셀을 지나 보통 나가는 방향. `ㅉ`, `ㅋ`은 언제나 반사된 방향
+pub fn out_dir(chr: KChar, dir: (i32, i32)) -> (i32, i32) {
+    if chr.0 == ' ' {
+        return dir;
+    }
+
+    let out = turn(chr.1, dir);
+    match chr.0 {
+        'ㅉ' | 'ㅋ' => (-out.0, -out.1),
+        _ => out,
+    }
+}
+
+pub fn dir_name(dir: (i32, i32)) -> String {
+    let name = match (dir.0.signum(), dir.1.signum()) {
+        (1, _) => "right",
+        (-1, _) => "left",
+        (_, -1) => "up",
+        _ => "down",
+    };
+    match dir.0.abs().max(dir.1.abs()) {
+        1 => name.to_string(),
+        n => format!("{} {}", name, n),
+    }
+}
+
+/// 도달할 수 있는 셀을 실행 구간으로 묶는다. 첫 구간이 시작점
+pub fn disassemble(aheui: &Aheui) -> Vec<Run> {
+    let analysis = DepthAnalysis::run(aheui);
+    let cell = |(x, y): (i32, i32)| aheui.src_map[y as usize][x as usize];
+
+    let mut succs: HashMap<Node, Vec<Node>> = HashMap::new();
+    let mut preds: HashMap<Node, HashSet<Node>> = HashMap::new();
+    for (from, to) in analysis.edges(aheui) {
+        let from = (from.cursor, from.dir);
+        let to = (to.cursor, to.dir);
+        let next = succs.entry(from).or_default();
+        if !next.contains(&to) {
+            next.push(to);
+        }
+        preds.entry(to).or_default().insert(from);
+    }
+    // 보통 방향을 먼저
+    for (&(cursor, dir), next) in succs.iter_mut() {
+        let out = out_dir(cell(cursor), dir);
+        next.sort_by_key(|&(c, d)| (d != out, c.1, c.0));
+    }
+
+    let entry: Node = ((0, 0), (0, 1));
+    if analysis.states.is_empty() {
+        return Vec::new();
+    }
+
+    let no_succ = Vec::new();
+    let succ = |node: &Node| succs.get(node).unwrap_or(&no_succ);
+    let starts = |node: &Node| {
+        *node == entry
+            || preds.get(node).is_none_or(|p| p.len() != 1)
+            || preds[node].iter().any(|p| succ(p).len() != 1)
+    };
+
+    // 보통 방향을 따라 깊이 우선으로 이름을 붙인다
+    let mut labels: HashMap<Node, usize> = HashMap::new();
+    let mut runs: Vec<Run> = Vec::new();
+    let mut stack = vec![entry];
+    while let Some(start) = stack.pop() {
+        if labels.contains_key(&start) {
+            continue;
+        }
+        labels.insert(start, runs.len());
+
+        let mut nodes = vec![start];
+        let mut last = start;
+        while let [next] = succ(&last).as_slice() {
+            if starts(next) {
+                break;
+            }
+            nodes.push(*next);
+            last = *next;
+        }
+
+        for next in succ(&last).iter().rev() {
+            stack.push(*next);
+        }
+        runs.push(Run {
+            label: runs.len(),
+            nodes,
+            exits: Vec::new(),
+        });
+    }
+
+    for run in runs.iter_mut() {
+        let &(cursor, dir) = run.nodes.last().unwrap();
+        let chr = cell(cursor);
+        let out = out_dir(chr, dir);
+        run.exits = succ(&(cursor, dir))
+            .iter()
+            .map(|next| {
+                let exit = match (next.1 == out, chr.0) {
+                    (true, _) => Exit::Next,
+                    (false, 'ㅊ') => Exit::Zero,
+                    (false, _) => Exit::Underflow,
+                };
+                (exit, labels[next])
+            })
+            .collect();
+    }
+
+    runs
+}
+
+/// 구간마다 `L<번호>:` 아래에 (좌표) 글자 명령 방향을 한 줄씩
+pub fn render(aheui: &Aheui, runs: &[Run]) -> String {
+    let mut out = String::new();
+
+    for run in runs {
+        out += &format!("L{}:\n", run.label);
+        for (idx, &((x, y), dir)) in run.nodes.iter().enumerate() {
+            let chr = aheui.src_map[y as usize][x as usize];
+            // 구간 안에서는 언제나 모자라 반사되는 셀도 있으니 다음 셀로 간 방향
+            let moved = match run.nodes.get(idx + 1) {
+                Some(&(_, next)) => next,
+                None => out_dir(chr, dir),
+            };
+            let line = format!(
+                "    {:<10} {}  {:<12} {}",
+                format!("({}, {})", x, y),
+                chr.3,
+                mnemonic(chr),
+                // 끝나는 셀은 나가지 않는다
+                if chr.0 == 'ㅎ' {
+                    String::new()
+                } else {
+                    dir_name(moved)
+                }
+            );
+            out += line.trim_end();
+            out += "\n";
+        }
+        for (exit, label) in &run.exits {
+            let reason = match exit {
+                Exit::Next => "",
+                Exit::Zero => "if zero ",
+                Exit::Underflow => "if underflow ",
+            };
+            out += &format!("        {}-> L{}\n", reason, label);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(src: &str) -> String {
+        let aheui = Aheui::new(src.to_string());
+        render(&aheui, &disassemble(&aheui))
+    }
+
+    #[test]
+    fn splits_runs_at_a_branch_and_wraps() {
+        // 0이면 위로 감싸 셋째 행을 왼쪽으로 지나며 다시 감싼다
+        let expected = "\
+L0:
+    (0, 0)     방  input num    right
+    (1, 0)     추  brz          down
+        -> L1
+        if zero -> L2
+L1:
+    (1, 1)     반  push 2       right
+    (2, 1)     망  print num    right
+    (3, 1)     희  halt
+L2:
+    (1, 2)     어  nop          left
+    (0, 2)     번  push 2       left
+    (3, 2)     멍  print num    left
+    (2, 2)     히  halt
+";
+        assert_eq!(listing("방추\nㅇ반망희\n번어히멍\n"), expected);
+    }
+
+    #[test]
+    fn joins_runs_after_a_comparison() {
+        // 0이면 비어 있는 스택에서 `ㅈ`이 반사되어 위로 감싸고, 두 길이 `망`에서 만난다
+        let expected = "\
+L0:
+    (0, 0)     방  input num    right
+    (1, 0)     방  input num    right
+    (2, 0)     주  cmp          down
+    (2, 1)     추  brz          down
+        -> L1
+        if zero -> L3
+L1:
+    (2, 2)     반  push 2       right
+        -> L2
+L2:
+    (0, 2)     망  print num    right
+    (1, 2)     희  halt
+L3:
+    (2, 0)     주  cmp          up
+    (2, 2)     반  push 2       right
+        -> L2
+";
+        assert_eq!(listing("방방주\nㅇㅇ추\n망희반\n"), expected);
+    }
+
+    #[test]
+    fn lists_underflow_exits() {
+        // 둘째 `뭉`부터는 값이 없어 반사되고, 위로 감싸 돌아온다
+        let expected = "\
+L0:
+    (0, 0)     방  input num    right
+    (1, 0)     뭉  print num    down
+    (1, 1)     뭉  print num    down
+        if underflow -> L1
+L1:
+    (1, 0)     뭉  print num    up
+    (1, 1)     뭉  print num    down
+        if underflow -> L1
+";
+        assert_eq!(listing("방뭉\n어뭉\n"), expected);
+    }
+
+    #[test]
+    fn lists_nothing_for_an_empty_grid() {
+        assert_eq!(listing(""), "");
+    }
+}
@@ -0,0 +1,305 @@
+use crate::aheui::{Aheui, ExitCode};
+use crate::rng::Rng;
+
+/// 입력을 메모리에서 읽어 한 번 실행한 결과
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub output: String,
+    /// 걸음 한도 안에 끝나지 않았으면 `None`
+    pub exit: Option<ExitCode>,
+}
+
+/// 실행 전 상태의 `aheui`를 복제해 `input`으로 최대 `steps` 걸음 실행.
+/// 빈 격자는 아무것도 하지 않고 끝난 것으로 본다
+pub fn run(aheui: &Aheui, input: &str, steps: usize) -> Outcome {
+    if aheui.src_mat.0 == 0 || aheui.src_mat.1 == 0 {
+        return Outcome {
+            output: String::new(),
+            exit: Some(ExitCode::Success(0)),
+        };
+    }
+
+    let mut runner = aheui.clone();
+    runner.debug(true);
+    runner.set_input(input.to_string());
+
+    while !runner.ended && runner.step < steps {
+        runner.next();
+    }
+
+    Outcome {
+        output: runner.result,
+        exit: runner.exit_code,
+    }
+}
+
+impl Outcome {
+    /// 한쪽만 한도 안에 끝나면 다른 것으로 본다. 둘 다 한도에 걸렸으면 출력의 앞부분만 비교
+    pub fn differs(&self, other: &Outcome) -> bool {
+        match (self.exit, other.exit) {
+            (None, None) => {
+                !(self.output.starts_with(&other.output) || other.output.starts_with(&self.output))
+            }
+            _ => self != other,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Counterexample {
+    pub input: String,
+    pub left: Outcome,
+    pub right: Outcome,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Bounds {
+    /// 나열할 입력의 최대 줄 수. 무작위 입력은 이 두 배까지 길어질 수 있다
+    pub lines: usize,
+    /// 한 번 실행의 최대 걸음
+    pub steps: usize,
+    /// 나열 뒤에 더 시도할 무작위 입력 수
+    pub random: usize,
+    pub seed: u64,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Self {
+            lines: 2,
+            steps: 100_000,
+            random: 100,
+            seed: 0x5eed,
+        }
+    }
+}
+
+/// 나열할 때 쓰는 입력 한 줄
+const LINES: [&str; 8] = ["0", "1", "-1", "2", "10", "a", "A", " "];
+
+/// 두 프로그램이 한도 안의 모든 입력에서 같은 출력과 종료값을 내는지 확인.
+/// 다르면 가장 짧게 줄인 반례를 돌려준다
+pub fn check(left: &Aheui, right: &Aheui, bounds: Bounds) -> (usize, Option<Counterexample>) {
+    let mut tried = 0;
+    let mut differs = |input: &str| {
+        tried += 1;
+        let l = run(left, input, bounds.steps);
+        let r = run(right, input, bounds.steps);
+        l.differs(&r).then(|| Counterexample {
+            input: input.to_string(),
+            left: l,
+            right: r,
+        })
+    };
+
+    // 짧은 입력부터 나열하므로 처음 찾은 반례의 줄 수가 가장 적다
+    for len in 0..=bounds.lines {
+        let mut idx = vec![0; len];
+        loop {
+            let input: String = idx.iter().map(|&i| format!("{}\n", LINES[i])).collect();
+            if let Some(found) = differs(&input) {
+                return (tried, Some(found));
+            }
+
+            if !next_index(&mut idx, LINES.len()) {
+                break;
+            }
+        }
+    }
+
+    let mut rng = Rng::new(bounds.seed);
+    for _ in 0..bounds.random {
+        let lines: Vec<String> = (0..=rng.below(bounds.lines.max(1) * 2))
+            .map(|_| random_line(&mut rng))
+            .collect();
+        if let Some(found) = differs(&(lines.join("\n") + "\n")) {
+            let found = shrink(left, right, lines, bounds.steps).unwrap_or(found);
+            return (tried, Some(found));
+        }
+    }
+
+    (tried, None)
+}
+
+/// 줄을 지우거나 0으로 바꿔도 여전히 다르면 줄인다
+fn shrink(
+    left: &Aheui,
+    right: &Aheui,
+    mut lines: Vec<String>,
+    steps: usize,
+) -> Option<Counterexample> {
+    let test = |lines: &[String]| {
+        let input = lines.iter().map(|l| format!("{}\n", l)).collect::<String>();
+        let l = run(left, &input, steps);
+        let r = run(right, &input, steps);
+        l.differs(&r).then_some(Counterexample {
+            input,
+            left: l,
+            right: r,
+        })
+    };
+
+    let mut best = test(&lines)?;
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in (0..lines.len()).rev() {
+            let mut fewer = lines.clone();
+            fewer.remove(i);
+            if let Some(found) = test(&fewer) {
+                (lines, best, changed) = (fewer, found, true);
+                continue;
+            }
+
+            if lines[i] != "0" {
+                let mut simpler = lines.clone();
+                simpler[i] = "0".to_string();
+                if let Some(found) = test(&simpler) {
+                    (lines, best, changed) = (simpler, found, true);
+                }
+            }
+        }
+    }
+
+    Some(best)
+}
+
+/// 자릿수 올림으로 다음 조합. 마지막이면 `false`
+fn next_index(idx: &mut [usize], base: usize) -> bool {
+    for i in idx.iter_mut().rev() {
+        *i += 1;
+        if *i < base {
+            return true;
+        }
+        *i = 0;
+    }
+    false
+}
+
+fn random_line(rng: &mut Rng) -> String {
+    match rng.below(3) {
+        0 => (rng.below(2001) as i32 - 1000).to_string(),
+        1 => char::from(b' ' + rng.below(95) as u8).to_string(),
+        _ => (0..rng.below(6))
+            .map(|_| char::from(b'a' + rng.below(26) as u8))
+            .collect(),
+    }
+}
+
+/// 다시 쓰는 기능들이 동작을 지키는지 볼 예제 프로그램
+#[cfg(test)]
+pub(crate) const SAMPLES: [&str; 8] = [
+    include_str!("../tests/standard/queue.aheui"),
+    include_str!("../tests/standard/storage.aheui"),
+    include_str!("../tests/standard/vowel-advanced.aheui"),
+    include_str!("../tests/standard/border.aheui"),
+    include_str!("../tests/standard/default-direction-nonhangul.aheui"),
+    include_str!("../tests/factorial/factorial.aheui"),
+    include_str!("../tests/hello-world/hello-world.puzzlet.aheui"),
+    include_str!("../tests/literature/sijo-div.aheui"),
+];
+
+/// 짧은 입력들에서 두 프로그램의 출력과 종료값이 같은지
+#[cfg(test)]
+pub(crate) fn assert_equivalent(left: &str, right: &str) {
+    let bounds = Bounds {
+        lines: 1,
+        steps: 1_000_000,
+        random: 5,
+        seed: 1,
+    };
+    let (_, found) = check(
+        &Aheui::new(left.to_string()),
+        &Aheui::new(right.to_string()),
+        bounds,
+    );
+    if let Some(found) = found {
+        panic!(
+            "{:?} gives {:?} and {:?}\n{}\n---\n{}",
+            found.input, found.left, found.right, left, right
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aheui(src: &str) -> Aheui {
+        Aheui::new(src.to_string())
+    }
+
+    // 읽은 수를 종료값으로 끝낸다
+    const ECHO_EXIT: &str = "방희";
+    // 읽은 수가 100 이상일 때만 그 수를 출력하고 0으로 끝낸다
+    const LOUD_ABOVE_100: &str =
+        "방빠발발따반반따따자추\nㅇㅇㅇㅇㅇㅇㅇㅇㅇㅇ뭉\nㅇㅇㅇㅇㅇㅇㅇㅇㅇㅇ흐\n";
+
+    #[test]
+    fn runs_an_empty_grid() {
+        for src in ["", "\n\n"] {
+            let outcome = run(&aheui(src), "1\n", 100);
+            assert_eq!(outcome.output, "");
+            assert_eq!(outcome.exit, Some(ExitCode::Success(0)));
+        }
+        assert!(
+            check(&aheui(""), &aheui("희"), Bounds::default())
+                .1
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn accepts_the_same_behavior_in_another_shape() {
+        let bounds = Bounds::default();
+        let (tried, found) = check(&aheui("방망희"), &aheui("붕\n뭉\n흐\n"), bounds);
+        assert!(found.is_none());
+        // 0, 1, 2줄을 모두 나열한 뒤 무작위 입력
+        assert_eq!(tried, 1 + 8 + 64 + bounds.random);
+    }
+
+    #[test]
+    fn accepts_every_sample_against_itself() {
+        for src in SAMPLES {
+            assert_equivalent(src, src);
+        }
+    }
+
+    #[test]
+    fn finds_the_shortest_enumerated_difference() {
+        let (_, found) = check(&aheui("방망희"), &aheui("방빠망망희"), Bounds::default());
+        let found = found.expect("differs");
+        assert_eq!(found.input, "");
+        assert_ne!(found.left, found.right);
+    }
+
+    #[test]
+    fn shrinks_a_random_counterexample() {
+        let (left, right) = (aheui(ECHO_EXIT), aheui(LOUD_ABOVE_100));
+        for line in LINES {
+            let input = format!("{}\n", line);
+            assert!(!run(&left, &input, 1000).differs(&run(&right, &input, 1000)));
+        }
+
+        let (_, found) = check(&left, &right, Bounds::default());
+        let found = found.expect("differs");
+        // 한 줄만 남고, 그 줄을 지우거나 0으로 바꾸면 같아진다
+        let n: i32 = found.input.strip_suffix('\n').unwrap().parse().unwrap();
+        assert!(n >= 100, "{:?}", found.input);
+        assert_eq!(found.right.output, n.to_string());
+        for simpler in ["", "0\n"] {
+            assert!(!run(&left, simpler, 1000).differs(&run(&right, simpler, 1000)));
+        }
+    }
+
+    #[test]
+    fn compares_only_the_common_prefix_of_unfinished_runs() {
+        let output = |output: &str, exit| Outcome {
+            output: output.to_string(),
+            exit,
+        };
+        assert!(!output("12", None).differs(&output("123", None)));
+        assert!(output("12", None).differs(&output("13", None)));
+        assert!(output("12", None).differs(&output("123", Some(ExitCode::Success(0)))));
+    }
+}
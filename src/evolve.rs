@@ -0,0 +1,346 @@
+use std::collections::HashSet;
+
+use crate::aheui::{Aheui, ExitCode};
+use crate::hangul::jamo::{_END, _MIDDLE, _START};
+use crate::hangul::{KChar, assemble_jamo, disassemble_jamo};
+use crate::rng::Rng;
+
+/// 격자. 행마다 같은 길이
+pub type Grid = Vec<Vec<KChar>>;
+
+/// 목표에 없는 글자나 모자란 글자 하나의 거리
+const MISSING: u64 = 128;
+/// 상태 반복을 검사하는 간격. 끝나지 않는 후보를 일찍 자른다
+const LOOP_INTERVAL: usize = 64;
+/// 부모를 고를 때 겨루는 후보 수
+const TOURNAMENT: usize = 3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    pub population: usize,
+    pub generations: usize,
+    /// 한 번 실행의 최대 걸음
+    pub steps: usize,
+    /// 격자의 최대 크기
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            population: 200,
+            generations: 1000,
+            steps: 1000,
+            width: 8,
+            height: 8,
+            seed: 0x5eed,
+        }
+    }
+}
+
+/// 후보 하나를 실행한 결과
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub grid: Grid,
+    pub output: String,
+    /// 걸음 한도 안에 끝났는지
+    pub ended: bool,
+    /// 출력과 목표의 거리. 0이면 같다
+    pub distance: u64,
+}
+
+impl Candidate {
+    /// 작을수록 좋다. 거리, 끝났는지, 크기 순
+    fn key(&self) -> (u64, bool, usize) {
+        (self.distance, !self.ended, self.size())
+    }
+
+    pub fn size(&self) -> usize {
+        self.grid.len() * self.grid.first().map_or(0, Vec::len)
+    }
+
+    pub fn src(&self) -> String {
+        self.grid
+            .iter()
+            .map(|row| row.iter().map(|c| c.3).collect::<String>() + "\n")
+            .collect()
+    }
+}
+
+/// 출력이 목표에서 얼마나 먼지. 자리마다 글자 값의 차이를 `MISSING`까지 더한다
+pub fn distance(output: &str, target: &str) -> u64 {
+    let (a, b): (Vec<char>, Vec<char>) = (output.chars().collect(), target.chars().collect());
+
+    (0..a.len().max(b.len()))
+        .map(|i| match (a.get(i), b.get(i)) {
+            (Some(&x), Some(&y)) => (x as i64 - y as i64).unsigned_abs().min(MISSING),
+            _ => MISSING,
+        })
+        .sum()
+}
+
+/// 실행기 하나를 격자만 바꿔 가며 다시 쓴다
+#[derive(Debug, Clone)]
+pub struct Runner {
+    aheui: Aheui,
+    input: String,
+    steps: usize,
+}
+
+impl Runner {
+    pub fn new(input: &str, steps: usize) -> Self {
+        let mut aheui = Aheui::new(String::new());
+        aheui.debug(true);
+        aheui.detect_loops(Some(LOOP_INTERVAL));
+
+        Self {
+            aheui,
+            input: input.to_string(),
+            steps,
+        }
+    }
+
+    /// (출력, 끝났는지)
+    pub fn run(&mut self, grid: &Grid) -> (&str, bool) {
+        let aheui = &mut self.aheui;
+        aheui.src_map.clone_from(grid);
+        aheui.src_mat = (grid.first().map_or(0, Vec::len), grid.len());
+        aheui.reset();
+        aheui.set_input(self.input.clone());
+
+        while !aheui.ended && aheui.step < self.steps {
+            aheui.next();
+        }
+
+        let ended = matches!(aheui.exit_code, Some(ExitCode::Success(_)));
+        (&aheui.result, ended)
+    }
+}
+
+fn cell(start: char, vowel: char, end: (char, char)) -> KChar {
+    // ㅎ 저장공간(통로)은 인터프리터에 없으므로 고르거나 옮기지 않는다
+    let end = match (start, end) {
+        ('ㅅ' | 'ㅆ', ('ㅎ', ' ')) => _END[0],
+        _ => end,
+    };
+    disassemble_jamo(assemble_jamo(start, vowel, end))
+}
+
+fn random_cell(rng: &mut Rng) -> KChar {
+    let start = *rng.pick(&_START).unwrap();
+    let vowel = *rng.pick(&_MIDDLE).unwrap();
+    // 받침은 `ㅂ`, `ㅅ`, `ㅆ`에만 뜻이 있으니 절반은 비운다
+    let end = match rng.below(2) {
+        0 => _END[0],
+        _ => *rng.pick(&_END).unwrap(),
+    };
+    cell(start, vowel, end)
+}
+
+fn random_grid(rng: &mut Rng, settings: &Settings) -> Grid {
+    let w = 1 + rng.below(settings.width);
+    let h = 1 + rng.below(settings.height);
+    (0..h)
+        .map(|_| (0..w).map(|_| random_cell(rng)).collect())
+        .collect()
+}
+
+/// 셀 하나의 자모를 바꾸거나, 셀을 새로 뽑거나, 행이나 열을 넣고 빼거나, 두 셀을 바꾼다
+fn mutate(grid: &mut Grid, rng: &mut Rng, settings: &Settings) {
+    let (w, h) = (grid[0].len(), grid.len());
+    let (x, y) = (rng.below(w), rng.below(h));
+
+    match rng.below(8) {
+        0..=2 => {
+            let KChar(start, vowel, end, _) = grid[y][x];
+            let chr = match (start, rng.below(3)) {
+                // 한글이 아닌 칸은 새로 뽑는다
+                (' ', _) => random_cell(rng),
+                (_, 0) => cell(*rng.pick(&_START).unwrap(), vowel, end),
+                (_, 1) => cell(start, *rng.pick(&_MIDDLE).unwrap(), end),
+                _ => cell(start, vowel, *rng.pick(&_END).unwrap()),
+            };
+            grid[y][x] = chr;
+        }
+        3 => grid[y][x] = random_cell(rng),
+        4 if h < settings.height => {
+            let row = (0..w).map(|_| random_cell(rng)).collect();
+            grid.insert(y, row);
+        }
+        5 if w < settings.width => {
+            for row in grid.iter_mut() {
+                row.insert(x, random_cell(rng));
+            }
+        }
+        6 if h > 1 => {
+            grid.remove(y);
+        }
+        7 if w > 1 => {
+            for row in grid.iter_mut() {
+                row.remove(x);
+            }
+        }
+        _ => {
+            let (x2, y2) = (rng.below(w), rng.below(h));
+            let tmp = grid[y][x];
+            grid[y][x] = grid[y2][x2];
+            grid[y2][x2] = tmp;
+        }
+    }
+}
+
+/// 출력이 `target`인 작은 격자를 찾는다. 같은 것을 찾았으면 그중 가장 작은 것,
+/// 못 찾았으면 가장 가까운 것과 그것을 찾은 세대. `report`는 나아질 때마다 불린다
+pub fn evolve(
+    target: &str,
+    input: &str,
+    settings: Settings,
+    mut report: impl FnMut(usize, &Candidate),
+) -> (Candidate, usize) {
+    let mut rng = Rng::new(settings.seed);
+    let mut runner = Runner::new(input, settings.steps);
+    let mut evaluate = |grid: Grid| {
+        let (output, ended) = runner.run(&grid);
+        Candidate {
+            distance: distance(output, target),
+            output: output.to_string(),
+            ended,
+            grid,
+        }
+    };
+
+    let population = settings.population.max(1);
+    let mut pool: Vec<Candidate> = (0..population)
+        .map(|_| evaluate(random_grid(&mut rng, &settings)))
+        .collect();
+    pool.sort_by_key(Candidate::key);
+    let mut best = (pool[0].clone(), 0);
+    report(0, &best.0);
+
+    for generation in 1..=settings.generations {
+        let mut children = Vec::with_capacity(population);
+        for _ in 0..population {
+            let parent = (0..TOURNAMENT)
+                .map(|_| rng.below(pool.len()))
+                .min()
+                .unwrap();
+            let mut grid = pool[parent].grid.clone();
+            for _ in 0..1 + rng.below(3) {
+                mutate(&mut grid, &mut rng, &settings);
+            }
+            children.push(evaluate(grid));
+        }
+
+        // 부모와 자식 중 좋은 것만 남기되 같은 격자는 하나만
+        pool.extend(children);
+        pool.sort_by_key(Candidate::key);
+        let mut seen = HashSet::new();
+        pool.retain(|c| seen.insert(c.src()));
+        pool.truncate(population);
+
+        if pool[0].key() < best.0.key() {
+            best = (pool[0].clone(), generation);
+            report(generation, &best.0);
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(width: usize, height: usize) -> Settings {
+        Settings {
+            population: 20,
+            generations: 20,
+            steps: 200,
+            width,
+            height,
+            seed: 1,
+        }
+    }
+
+    #[test]
+    fn measures_each_position_up_to_missing() {
+        assert_eq!(distance("12", "12"), 0);
+        assert_eq!(distance("13", "12"), 1);
+        // 모자라거나 남는 글자는 하나에 `MISSING`
+        assert_eq!(distance("1", "12"), MISSING);
+        assert_eq!(distance("123", "1"), 2 * MISSING);
+        assert_eq!(distance("", ""), 0);
+        // 아주 다른 글자도 `MISSING`을 넘지 않는다
+        assert_eq!(distance("가", "1"), MISSING);
+        assert_eq!(distance("a", "b가"), 1 + MISSING);
+    }
+
+    #[test]
+    fn mutations_stay_rectangular_and_within_bounds() {
+        let settings = settings(4, 3);
+        let mut rng = Rng::new(7);
+        for _ in 0..50 {
+            let mut grid = random_grid(&mut rng, &settings);
+            for _ in 0..200 {
+                mutate(&mut grid, &mut rng, &settings);
+                let w = grid[0].len();
+                assert!((1..=settings.width).contains(&w));
+                assert!((1..=settings.height).contains(&grid.len()));
+                assert!(grid.iter().all(|row| row.len() == w));
+                // 고른 저장공간이 ㅎ인 셀은 만들지 않는다
+                let selects_heut = |c: &KChar| matches!(c.0, 'ㅅ' | 'ㅆ') && c.2 == ('ㅎ', ' ');
+                assert!(!grid.iter().flatten().any(selects_heut));
+            }
+        }
+    }
+
+    #[test]
+    fn reused_runner_matches_a_fresh_interpreter() {
+        let settings = settings(5, 5);
+        let mut rng = Rng::new(3);
+        let mut runner = Runner::new("3\nab\n", settings.steps);
+        for _ in 0..300 {
+            let grid = random_grid(&mut rng, &settings);
+            let candidate = Candidate {
+                grid: grid.clone(),
+                output: String::new(),
+                ended: false,
+                distance: 0,
+            };
+
+            let mut fresh = Aheui::new(candidate.src());
+            fresh.debug(true);
+            fresh.detect_loops(Some(LOOP_INTERVAL));
+            fresh.set_input("3\nab\n".to_string());
+            while !fresh.ended && fresh.step < settings.steps {
+                fresh.next();
+            }
+            let ended = matches!(fresh.exit_code, Some(ExitCode::Success(_)));
+
+            assert_eq!(
+                runner.run(&grid),
+                (fresh.result.as_str(), ended),
+                "{}",
+                candidate.src()
+            );
+        }
+    }
+
+    #[test]
+    fn finds_trivial_targets() {
+        // 작은 무리는 아무것도 출력하지 않는 1x1 격자로 몰리기 쉽다
+        let settings = Settings {
+            generations: 100,
+            steps: 200,
+            ..Settings::default()
+        };
+        for target in ["", "1"] {
+            let (best, _) = evolve(target, "", settings, |_, _| {});
+            assert_eq!(best.distance, 0, "{}", best.src());
+            assert!(best.ended);
+            assert_eq!(best.output, target);
+        }
+    }
+}
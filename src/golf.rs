@@ -0,0 +1,440 @@
+use std::collections::HashSet;
+
+use crate::aheui::{Aheui, ExitCode, turn};
+use crate::constant::Synthesizer;
+use crate::equiv::run;
+use crate::hangul::{FILLER, KChar, assemble_jamo, count_lines_in_char};
+use crate::minify::minify;
+
+/// 입력과 그때 나와야 하는 출력. `tests/`처럼 끝의 줄바꿈은 보지 않는다
+#[derive(Debug, Clone)]
+pub struct Case {
+    pub name: String,
+    pub input: String,
+    pub output: String,
+}
+
+type Grid = Vec<Vec<char>>;
+
+/// 원래 프로그램이 각 경우에 낸 종료값과 새 후보에게 줄 걸음 한도
+struct Judge<'a> {
+    cases: &'a [Case],
+    exits: Vec<Option<ExitCode>>,
+    steps: Vec<usize>,
+}
+
+impl Judge<'_> {
+    fn passes(&self, src: &str) -> bool {
+        let aheui = Aheui::new(src.to_string());
+        if aheui.src_mat.0 == 0 || aheui.src_mat.1 == 0 {
+            return false;
+        }
+
+        self.cases.iter().enumerate().all(|(idx, case)| {
+            let outcome = run(&aheui, &case.input, self.steps[idx]);
+            outcome.exit == self.exits[idx]
+                && outcome.output.trim_end_matches('\n') == case.output.trim_end_matches('\n')
+        })
+    }
+}
+
+/// 줄바꿈을 뺀 글자 수
+pub fn size(src: &str) -> usize {
+    src.lines().map(|line| line.chars().count()).sum()
+}
+
+/// 작을수록 좋다. 글자 수가 같으면 한글 셀이 적은 쪽이 나중에 더 줄일 수 있다
+fn cost(src: &str) -> (usize, usize) {
+    (size(src), src.chars().filter(|&c| is_syllable(c)).count())
+}
+
+/// 행 끝의 한글이 아닌 칸과 끝의 빈 행은 쓰지 않는다
+fn render(grid: &Grid) -> String {
+    let mut lines: Vec<String> = grid
+        .iter()
+        .map(|row| {
+            let len = row
+                .iter()
+                .rposition(|&c| is_syllable(c))
+                .map_or(0, |x| x + 1);
+            row[..len].iter().collect()
+        })
+        .collect();
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    lines.iter().map(|line| line.clone() + "\n").collect()
+}
+
+fn is_syllable(c: char) -> bool {
+    ('가'..='힣').contains(&c)
+}
+
+fn grid_of(aheui: &Aheui) -> Grid {
+    aheui
+        .src_map
+        .iter()
+        .map(|row| row.iter().map(|c| c.3).collect())
+        .collect()
+}
+
+/// 실행 중 멈추지 않고 한 방향으로 지나간 셀들 중, 빈 스택에서 시작해 값 하나만
+/// 남기는 구간(수를 만드는 명령들)을 더 짧은 것으로 바꾼 후보
+fn constants(aheui: &Aheui, cases: &[Case], steps: &[usize]) -> Vec<Grid> {
+    let mut synth = Synthesizer::default();
+    let mut seen = HashSet::new();
+    let mut found = vec![];
+
+    for (case, &limit) in cases.iter().zip(steps) {
+        let mut runner = aheui.clone();
+        runner.debug(true);
+        runner.set_input(case.input.clone());
+
+        // 지금 구간의 (셀, 값을 모으는 스택)
+        let mut cells: Vec<(i32, i32)> = vec![];
+        let mut values: Vec<i32> = vec![];
+        let mut delta = (0, 0);
+        while !runner.ended && runner.step < limit {
+            let (cursor, sel) = (runner.cursor, runner.sel);
+            let chr = *runner.current();
+            runner.next();
+
+            let step = (runner.cursor.0 - cursor.0, runner.cursor.1 - cursor.1);
+            let unit = step.0.abs() + step.1.abs() == 1 && sel != 21;
+            let extends = unit
+                && (cells.is_empty() || step == delta)
+                && push_value(chr.0, chr.2, &mut values).is_some();
+            if !extends {
+                // 이 셀에서 새 구간이 시작될 수도 있다
+                cells.clear();
+                values.clear();
+                if !unit || push_value(chr.0, chr.2, &mut values).is_none() {
+                    values.clear();
+                    continue;
+                }
+            }
+            cells.push(cursor);
+            delta = step;
+
+            if values.len() == 1 && cells.len() > 1 && seen.insert(cells.clone()) {
+                let ops = synth.synthesize(values[0]);
+                if ops.len() < cells.len() {
+                    let mut grid = grid_of(aheui);
+                    for (idx, &(x, y)) in cells.iter().enumerate() {
+                        let vowel = aheui.src_map[y as usize][x as usize].1;
+                        grid[y as usize][x as usize] = match ops.get(idx) {
+                            Some(&(start, end)) => assemble_jamo(start, vowel, end),
+                            // 방향을 바꾸지 않는 셀은 비운다
+                            None if turn(vowel, delta) == delta => FILLER,
+                            None => assemble_jamo('ㅇ', vowel, (' ', ' ')),
+                        };
+                    }
+                    found.push(grid);
+                }
+            }
+        }
+    }
+
+    found
+}
+
+/// 값을 만드는 명령이면 `values`에서 실행하고 `Some`. 밖의 값이 필요하면 `None`
+fn push_value(start: char, end: (char, char), values: &mut Vec<i32>) -> Option<()> {
+    let need = match start {
+        'ㅂ' => 0,
+        'ㅃ' => 1,
+        'ㄷ' | 'ㄸ' | 'ㅌ' | 'ㄴ' | 'ㄹ' | 'ㅍ' => 2,
+        _ => return None,
+    };
+    if values.len() < need || (start == 'ㅂ' && matches!(end, ('ㅇ', ' ') | ('ㅎ', ' '))) {
+        return None;
+    }
+
+    match start {
+        'ㅂ' => values.push(count_lines_in_char(end)),
+        'ㅃ' => values.push(*values.last()?),
+        'ㅍ' => {
+            let len = values.len();
+            values.swap(len - 1, len - 2);
+        }
+        _ => {
+            let (top, second) = (values.pop()?, values.pop()?);
+            values.push(match start {
+                'ㄷ' => second.wrapping_add(top),
+                'ㄸ' => second.wrapping_mul(top),
+                'ㅌ' => second.wrapping_sub(top),
+                _ if top == 0 => return None,
+                'ㄴ' => second.wrapping_div(top),
+                _ => second.wrapping_rem(top),
+            });
+        }
+    }
+    Some(())
+}
+
+/// 행이나 열, 셀 하나를 지우거나 셀을 비운 후보. 클 것부터
+fn deletions(grid: &Grid) -> Vec<Grid> {
+    let (w, h) = (grid[0].len(), grid.len());
+    let mut found = vec![];
+
+    for y in 0..h {
+        let mut g = grid.clone();
+        g.remove(y);
+        found.push(g);
+    }
+    for x in 0..w {
+        let mut g = grid.clone();
+        for row in g.iter_mut() {
+            row.remove(x);
+        }
+        found.push(g);
+    }
+    for y in 0..h {
+        for x in 0..w {
+            // 오른쪽을 당기거나 아래를 올린다
+            let mut g = grid.clone();
+            g[y].remove(x);
+            g[y].push(FILLER);
+            found.push(g);
+
+            let mut g = grid.clone();
+            for yy in y..h - 1 {
+                g[yy][x] = g[yy + 1][x];
+            }
+            g[h - 1][x] = FILLER;
+            found.push(g);
+
+            let mut g = grid.clone();
+            g[y][x] = FILLER;
+            found.push(g);
+        }
+    }
+
+    found
+}
+
+/// 셀 하나의 방향을 바꿔 한 경로를 같은 일을 하는 다른 경로로 보낸 뒤, `minify`로
+/// 더는 실행되지 않는 셀을 지운 후보. 지금보다 작아지는 것만
+fn merges(aheui: &Aheui) -> Vec<Grid> {
+    let grid = grid_of(aheui);
+    let before = cost(&render(&grid));
+    let mut found = vec![];
+
+    for (y, row) in aheui.src_map.iter().enumerate() {
+        for (x, &KChar(start, vowel, end, _)) in row.iter().enumerate() {
+            if start == ' ' {
+                continue;
+            }
+            for to in ['ㅏ', 'ㅓ', 'ㅗ', 'ㅜ', 'ㅑ', 'ㅕ', 'ㅛ', 'ㅠ'] {
+                if to == vowel {
+                    continue;
+                }
+                let mut g = grid.clone();
+                g[y][x] = assemble_jamo(start, to, end);
+                let merged = minify(&Aheui::new(render(&g)), FILLER);
+                if cost(&merged) < before {
+                    found.push(grid_of(&Aheui::new(merged)));
+                }
+            }
+        }
+    }
+
+    found
+}
+
+/// 모든 경우에 같은 출력과 종료값을 내는 가장 작은 격자를 찾는다.
+///
+/// 먼저 `minify`로 실행되지 않는 곳을 지우고, 수를 만드는 구간을 더 짧게 바꾸거나
+/// 행·열·셀을 지우거나 두 경로를 하나로 합친 후보 중 `cost`가 줄면서 모든 경우를
+/// 통과하는 것을 더 나아지지 않을 때까지 고른다. 원래 프로그램이 통과하지 못하는
+/// 경우가 있으면 그 이름
+pub fn golf(src: &str, cases: &[Case], steps: usize) -> Result<String, String> {
+    let original = Aheui::new(src.to_string());
+    if original.src_mat.0 == 0 || original.src_mat.1 == 0 {
+        // 빈 격자는 아무것도 출력하지 않고 끝나며 더 줄일 것도 없다
+        return match cases
+            .iter()
+            .find(|case| !case.output.trim_end_matches('\n').is_empty())
+        {
+            Some(case) => Err(case.name.clone()),
+            None => Ok(String::new()),
+        };
+    }
+    let mut exits = vec![];
+    let mut budgets = vec![];
+    for case in cases {
+        let mut runner = original.clone();
+        runner.debug(true);
+        runner.set_input(case.input.clone());
+        while !runner.ended && runner.step < steps {
+            runner.next();
+        }
+        if !runner.ended
+            || runner.result.trim_end_matches('\n') != case.output.trim_end_matches('\n')
+        {
+            return Err(case.name.clone());
+        }
+        exits.push(runner.exit_code);
+        // 조금 느려지는 것은 받아들인다
+        budgets.push(runner.step * 2 + 1000);
+    }
+    let judge = Judge {
+        cases,
+        exits,
+        steps: budgets,
+    };
+
+    let mut best = src.to_string();
+    let minified = minify(&original, FILLER);
+    if cost(&minified) < cost(&best) && judge.passes(&minified) {
+        best = minified;
+    }
+
+    loop {
+        let aheui = Aheui::new(best.clone());
+        let grid = grid_of(&aheui);
+        let candidates = constants(&aheui, cases, &judge.steps)
+            .into_iter()
+            .chain(deletions(&grid))
+            // 셀마다 `minify`를 돌리므로 앞에서 못 찾았을 때만 만든다
+            .chain(std::iter::once(&aheui).flat_map(merges));
+
+        let mut improved = false;
+        for candidate in candidates {
+            let text = render(&candidate);
+            if cost(&text) < cost(&best) && judge.passes(&text) {
+                best = text;
+                improved = true;
+                break;
+            }
+        }
+        if !improved {
+            return Ok(best);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equiv::{SAMPLES, assert_equivalent};
+
+    fn case(name: &str, input: &str, output: &str) -> Case {
+        Case {
+            name: name.to_string(),
+            input: input.to_string(),
+            output: output.to_string(),
+        }
+    }
+
+    /// 원래 프로그램이 빈 입력과 `1`에 내는 출력
+    fn cases_of(src: &str) -> Vec<Case> {
+        let aheui = Aheui::new(src.to_string());
+        ["", "1\n"]
+            .iter()
+            .map(|input| case(input, input, &run(&aheui, input, 100_000).output))
+            .collect()
+    }
+
+    #[test]
+    fn push_value_matches_the_interpreter() {
+        for ops in ["반받다", "밤밥따발타", "박반나받바라", "빠", "발밪빠따파타"]
+        {
+            let mut values = vec![];
+            let pushed = ops
+                .chars()
+                .map(disassemble)
+                .try_for_each(|(start, end)| push_value(start, end, &mut values));
+            let src = format!("{}망희", ops);
+            let output = run(&Aheui::new(src), "", 1000).output;
+            match pushed {
+                Some(()) => assert_eq!(values.last().unwrap().to_string(), output, "{}", ops),
+                None => assert_eq!(output, "", "{}", ops),
+            }
+        }
+        // 0으로 나누거나 입력을 읽는 구간은 만들지 않는다
+        for ops in ["반바나", "방", "밯"] {
+            let mut values = vec![];
+            let pushed = ops
+                .chars()
+                .map(disassemble)
+                .try_for_each(|(start, end)| push_value(start, end, &mut values));
+            assert!(pushed.is_none(), "{}", ops);
+        }
+    }
+
+    fn disassemble(c: char) -> (char, (char, char)) {
+        let KChar(start, _, end, _) = crate::hangul::disassemble_jamo(c);
+        (start, end)
+    }
+
+    #[test]
+    fn constants_keep_the_behavior() {
+        for src in SAMPLES {
+            let aheui = Aheui::new(src.to_string());
+            let cases = cases_of(src);
+            for grid in constants(&aheui, &cases, &[100_000, 100_000]) {
+                assert_equivalent(src, &render(&grid));
+            }
+        }
+        // 반받다따 → 8을 더 짧게 만든다
+        let src = "반받다반따망희";
+        let found = constants(&Aheui::new(src.to_string()), &cases_of(src), &[1000, 1000]);
+        assert!(found.iter().any(|grid| cost(&render(grid)) < cost(src)));
+    }
+
+    #[test]
+    fn deletions_keep_the_behavior_of_padding() {
+        let src = "방망희\n";
+        let padded = "ㅇㅇㅇ\n방망희ㅇ\nㅇㅇㅇ\n";
+        let grid = grid_of(&Aheui::new(padded.to_string()));
+        let found: Vec<String> = deletions(&grid).iter().map(render).collect();
+        assert!(found.iter().any(|text| text == src));
+        for text in found.iter().filter(|text| text.ends_with("방망희\n")) {
+            assert_equivalent(src, text);
+        }
+    }
+
+    #[test]
+    fn merges_one_branch_into_the_other() {
+        // 0이 아니면 아래로 내려가 둘째 행을, 0이면 위로 감싸 셋째 행을 지난다
+        let src = "방추\nㅇ반망희\n번어히멍\n";
+        let found: Vec<String> = merges(&Aheui::new(src.to_string()))
+            .iter()
+            .map(render)
+            .collect();
+        let merged = "방추\nㅇ반망희\nㅇ오\n";
+        assert!(found.iter().any(|text| text == merged), "{:?}", found);
+        assert_equivalent(src, merged);
+    }
+
+    #[test]
+    fn shrinks_a_padded_sample() {
+        // 입력을 읽지 않으므로 두 경우만으로 모든 입력에서 같아야 한다
+        for src in [SAMPLES[0], SAMPLES[2]] {
+            let padded: String = src.lines().map(|line| format!("{}ㅇㅇ\n", line)).collect();
+            let padded = format!("{}ㅇㅇㅇ\n", padded);
+            let golfed = golf(&padded, &cases_of(&padded), 100_000).unwrap();
+            assert!(size(&golfed) <= size(src), "{}", golfed);
+            assert_equivalent(src, &golfed);
+        }
+    }
+
+    #[test]
+    fn reports_a_case_the_original_fails() {
+        let cases = [case("right", "", "2"), case("wrong", "", "3")];
+        assert_eq!(golf("반망희", &cases[..1], 1000), Ok("반망희".to_string()));
+        assert_eq!(golf("반망희", &cases, 1000), Err("wrong".to_string()));
+    }
+
+    #[test]
+    fn keeps_an_empty_grid() {
+        for src in ["", "\n\n"] {
+            assert_eq!(golf(src, &[case("empty", "", "")], 1000), Ok(String::new()));
+            assert_eq!(
+                golf(src, &[case("text", "", "1")], 1000),
+                Err("text".to_string())
+            );
+        }
+    }
+}
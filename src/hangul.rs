@@ -24,6 +24,9 @@ pub mod jamo {
 }
 use jamo::*;
 
+/// 한글 음절이 아니어서 지나가기만 하는 칸
+pub const FILLER: char = 'ㅇ';
+
 pub fn count_lines_in_char(chr: (char, char)) -> i32 {
     match chr {
         ('ㄱ', ' ') | ('ㄴ', ' ') | ('ㅅ', ' ') => 2,
@@ -109,3 +112,8 @@ pub fn assemble_jamo(start: char, middle: char, end: (char, char)) -> char {
 
     result_char.unwrap_or(' ')
 }
+
+/// 저장공간 이름 (아, 악, 앆, ...)
+pub fn storage_name(idx: usize) -> char {
+    assemble_jamo('ㅇ', 'ㅏ', _END[idx])
+}
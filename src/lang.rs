@@ -0,0 +1,1060 @@
+use std::collections::HashMap;
+
+use crate::asm::{Error, assemble_with_map};
+use crate::hangul::storage_name;
+use crate::sourcemap::{Pos, SourceMap};
+
+/// 식을 셈하는 스택. 변수는 나머지 저장공간을 하나씩 쓴다
+const EVAL: usize = 0;
+
+/// 변수에 줄 수 있는 저장공간. `앙`(통로)은 뺀다
+fn storages() -> impl Iterator<Item = usize> {
+    (0..27).filter(|&idx| idx != EVAL && idx != 21)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Num(i64),
+    Name(String),
+    Sym(&'static str),
+}
+
+const SYMBOLS: [&str; 20] = [
+    "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "<", ">", "!", "=", "(", ")", "{",
+    "}", ",",
+];
+
+fn tokenize(src: &str) -> Result<Vec<(Token, Pos)>, Error> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let pos = (line, i - line_start + 1);
+        let err = |message: String| Error { line, message };
+
+        if c == '\n' {
+            line += 1;
+            i += 1;
+            line_start = i;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == ';' {
+            tokens.push((Token::Sym(";"), pos));
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let digits: String = chars[start..i].iter().collect();
+            let n = digits
+                .parse()
+                .map_err(|_| err(format!("integer {} is too large", digits)))?;
+            tokens.push((Token::Num(n), pos));
+        } else if c == '\'' {
+            // 글자 하나. \n, \t, \\, \'
+            let (value, len) = match (chars.get(i + 1), chars.get(i + 2)) {
+                (Some('\\'), Some('n')) => ('\n', 2),
+                (Some('\\'), Some('t')) => ('\t', 2),
+                (Some('\\'), Some(&e @ ('\\' | '\''))) => (e, 2),
+                (Some(&c), _) if c != '\\' && c != '\n' => (c, 1),
+                _ => return Err(err("invalid character literal".to_string())),
+            };
+            if chars.get(i + 1 + len) != Some(&'\'') {
+                return Err(err("unterminated character literal".to_string()));
+            }
+            tokens.push((Token::Num(value as i64), pos));
+            i += len + 2;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Name(chars[start..i].iter().collect()), pos));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let sym = SYMBOLS
+                .iter()
+                .find(|s| rest.starts_with(**s))
+                .ok_or_else(|| err(format!("unexpected character {:?}", c)))?;
+            tokens.push((Token::Sym(sym), pos));
+            i += sym.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Num(i32),
+    /// (이름, 줄)
+    Var(String, usize),
+    /// (이름, 인자, 줄)
+    Call(String, Vec<Expr>, usize),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+enum Stmt {
+    Assign(String, Expr),
+    Print(Expr),
+    Printc(Expr),
+    Read(String),
+    While(Expr, Block),
+    If(Expr, Block, Block),
+    Return(Option<Expr>),
+    Expr(Expr),
+}
+
+/// 문장마다 시작한 자리
+type Block = Vec<(Pos, Stmt)>;
+
+#[derive(Debug, Clone)]
+struct Function {
+    name: String,
+    params: Vec<String>,
+    body: Block,
+    line: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Program {
+    functions: Vec<Function>,
+    main: Block,
+}
+
+const KEYWORDS: [&str; 8] = [
+    "fn", "while", "if", "else", "return", "print", "printc", "read",
+];
+
+/// 아래로 갈수록 먼저 묶는 이항 연산자
+const BINARY: [&[&str]; 5] = [
+    &["||"],
+    &["&&"],
+    &["==", "!=", "<", "<=", ">", ">="],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+struct Parser {
+    tokens: Vec<(Token, Pos)>,
+    pos: usize,
+    in_function: bool,
+}
+
+impl Parser {
+    fn here(&self) -> Pos {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or((1, 1), |t| t.1)
+    }
+
+    fn line(&self) -> usize {
+        self.here().0
+    }
+
+    fn err(&self, message: String) -> Error {
+        Error {
+            line: self.line(),
+            message,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.0)
+    }
+
+    fn at(&self, sym: &str) -> bool {
+        matches!(self.peek(), Some(Token::Sym(s)) if *s == sym)
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Name(n)) if n == keyword)
+    }
+
+    fn eat(&mut self, sym: &str) -> bool {
+        let found = self.at(sym);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, sym: &str) -> Result<(), Error> {
+        if self.eat(sym) {
+            return Ok(());
+        }
+        Err(self.err(match self.peek() {
+            Some(token) => format!("expected {:?}, got {:?}", sym, token),
+            None => format!("expected {:?} at end of input", sym),
+        }))
+    }
+
+    fn name(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Some(Token::Name(n)) if !KEYWORDS.contains(&n.as_str()) => {
+                let n = n.clone();
+                self.pos += 1;
+                Ok(n)
+            }
+            token => Err(self.err(format!("expected a name, got {:?}", token))),
+        }
+    }
+
+    fn program(&mut self) -> Result<Program, Error> {
+        let mut functions: Vec<Function> = vec![];
+        let mut main = vec![];
+
+        while self.peek().is_some() {
+            if self.at_keyword("fn") {
+                let line = self.line();
+                self.pos += 1;
+                let name = self.name()?;
+                if functions.iter().any(|f| f.name == name) {
+                    return Err(self.err(format!("duplicate function {:?}", name)));
+                }
+                self.expect("(")?;
+                let mut params = vec![];
+                while !self.eat(")") {
+                    if !params.is_empty() {
+                        self.expect(",")?;
+                    }
+                    let param = self.name()?;
+                    if params.contains(&param) {
+                        return Err(self.err(format!("duplicate parameter {:?}", param)));
+                    }
+                    params.push(param);
+                }
+                self.in_function = true;
+                let body = self.block()?;
+                self.in_function = false;
+                functions.push(Function {
+                    name,
+                    params,
+                    body,
+                    line,
+                });
+            } else {
+                main.push((self.here(), self.stmt()?));
+            }
+        }
+
+        Ok(Program { functions, main })
+    }
+
+    fn block(&mut self) -> Result<Block, Error> {
+        self.expect("{")?;
+        let mut stmts = vec![];
+        while !self.eat("}") {
+            if self.peek().is_none() {
+                return Err(self.err("unclosed block".to_string()));
+            }
+            stmts.push((self.here(), self.stmt()?));
+        }
+        Ok(stmts)
+    }
+
+    fn stmt(&mut self) -> Result<Stmt, Error> {
+        let keyword = match self.peek() {
+            Some(Token::Name(n)) if KEYWORDS.contains(&n.as_str()) => n.clone(),
+            _ => String::new(),
+        };
+        if !keyword.is_empty() {
+            self.pos += 1;
+        }
+
+        let stmt = match keyword.as_str() {
+            "while" => return Ok(Stmt::While(self.expr(0)?, self.block()?)),
+            "if" => {
+                let cond = self.expr(0)?;
+                let then = self.block()?;
+                let other = if self.at_keyword("else") {
+                    self.pos += 1;
+                    if self.at_keyword("if") {
+                        vec![(self.here(), self.stmt()?)]
+                    } else {
+                        self.block()?
+                    }
+                } else {
+                    vec![]
+                };
+                return Ok(Stmt::If(cond, then, other));
+            }
+            "return" => {
+                if !self.in_function {
+                    return Err(self.err("return outside a function".to_string()));
+                }
+                Stmt::Return(if self.at(";") {
+                    None
+                } else {
+                    Some(self.expr(0)?)
+                })
+            }
+            "print" => Stmt::Print(self.expr(0)?),
+            "printc" => Stmt::Printc(self.expr(0)?),
+            "read" => Stmt::Read(self.name()?),
+            "fn" | "else" => return Err(self.err(format!("unexpected {:?}", keyword))),
+            _ => {
+                let assigns = matches!(self.tokens.get(self.pos + 1), Some((Token::Sym("="), _)));
+                if assigns {
+                    let name = self.name()?;
+                    self.pos += 1;
+                    Stmt::Assign(name, self.expr(0)?)
+                } else {
+                    Stmt::Expr(self.expr(0)?)
+                }
+            }
+        };
+        self.expect(";")?;
+
+        Ok(stmt)
+    }
+
+    fn expr(&mut self, level: usize) -> Result<Expr, Error> {
+        if level == BINARY.len() {
+            return self.unary();
+        }
+
+        let mut left = self.expr(level + 1)?;
+        while let Some(Token::Sym(sym)) = self.peek() {
+            let Some(op) = BINARY[level].iter().find(|op| *op == sym) else {
+                break;
+            };
+            self.pos += 1;
+            let right = self.expr(level + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, Error> {
+        if self.eat("-") {
+            // 가장 작은 정수도 쓸 수 있도록 수는 바로 음수로
+            if let Some(Token::Num(n)) = self.peek() {
+                let n = -*n;
+                self.pos += 1;
+                return self.num(n);
+            }
+            return Ok(Expr::Unary("-", Box::new(self.unary()?)));
+        }
+        if self.eat("!") {
+            return Ok(Expr::Unary("!", Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn num(&self, n: i64) -> Result<Expr, Error> {
+        i32::try_from(n)
+            .map(Expr::Num)
+            .map_err(|_| self.err(format!("integer {} does not fit in 32 bits", n)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, Error> {
+        let line = self.line();
+        match self.peek().cloned() {
+            Some(Token::Num(n)) => {
+                self.pos += 1;
+                self.num(n)
+            }
+            Some(Token::Sym("(")) => {
+                self.pos += 1;
+                let expr = self.expr(0)?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(Token::Name(_)) => {
+                let name = self.name()?;
+                if !self.eat("(") {
+                    return Ok(Expr::Var(name, line));
+                }
+                let mut args = vec![];
+                while !self.eat(")") {
+                    if !args.is_empty() {
+                        self.expect(",")?;
+                    }
+                    args.push(self.expr(0)?);
+                }
+                Ok(Expr::Call(name, args, line))
+            }
+            token => Err(self.err(format!("expected an expression, got {:?}", token))),
+        }
+    }
+}
+
+fn parse(src: &str) -> Result<Program, Error> {
+    Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+        in_function: false,
+    }
+    .program()
+}
+
+/// 대입하거나 읽어 들이는 변수를 처음 나온 순서로
+fn assigned<'a>(body: &'a [(Pos, Stmt)], vars: &mut Vec<&'a str>) {
+    for (_, stmt) in body {
+        match stmt {
+            Stmt::Assign(name, _) | Stmt::Read(name) if !vars.contains(&name.as_str()) => {
+                vars.push(name);
+            }
+            Stmt::While(_, body) => assigned(body, vars),
+            Stmt::If(_, then, other) => {
+                assigned(then, vars);
+                assigned(other, vars);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn exprs(body: &[(Pos, Stmt)]) -> Vec<&Expr> {
+    let mut out = vec![];
+    for (_, stmt) in body {
+        match stmt {
+            Stmt::Assign(_, e) | Stmt::Print(e) | Stmt::Printc(e) | Stmt::Expr(e) => out.push(e),
+            Stmt::Return(e) => out.extend(e),
+            Stmt::Read(_) => {}
+            Stmt::While(cond, body) => {
+                out.push(cond);
+                out.extend(exprs(body));
+            }
+            Stmt::If(cond, then, other) => {
+                out.push(cond);
+                out.extend(exprs(then));
+                out.extend(exprs(other));
+            }
+        }
+    }
+    out
+}
+
+/// 모르는 변수와 함수, 인자 수를 확인하고 부르는 함수의 이름을 모은다
+fn check<'a>(
+    expr: &'a Expr,
+    vars: &[&str],
+    functions: &HashMap<&str, &Function>,
+    calls: &mut Vec<(&'a str, usize)>,
+) -> Result<(), Error> {
+    match expr {
+        Expr::Num(_) => Ok(()),
+        Expr::Var(name, line) => {
+            if vars.contains(&name.as_str()) {
+                Ok(())
+            } else {
+                Err(Error {
+                    line: *line,
+                    message: format!("unknown variable {:?}", name),
+                })
+            }
+        }
+        Expr::Call(name, args, line) => {
+            let err = |message: String| Error {
+                line: *line,
+                message,
+            };
+            let function = functions
+                .get(name.as_str())
+                .ok_or_else(|| err(format!("unknown function {:?}", name)))?;
+            if function.params.len() != args.len() {
+                return Err(err(format!(
+                    "{} takes {} argument(s)",
+                    name,
+                    function.params.len()
+                )));
+            }
+            calls.push((name, *line));
+            args.iter()
+                .try_for_each(|arg| check(arg, vars, functions, calls))
+        }
+        Expr::Unary(_, e) => check(e, vars, functions, calls),
+        Expr::Binary(_, l, r) => {
+            check(l, vars, functions, calls)?;
+            check(r, vars, functions, calls)
+        }
+    }
+}
+
+/// 함수는 부른 자리에 펼치므로 저장공간을 함수마다 따로 정해 둔다
+struct Gen<'a> {
+    functions: HashMap<&'a str, &'a Function>,
+    /// 함수 이름(본문은 "") → 변수 → 저장공간
+    slots: HashMap<&'a str, HashMap<&'a str, usize>>,
+    /// 펼치는 중인 함수와 그 `return`이 갈 이름표
+    scopes: Vec<(&'a str, String)>,
+    labels: usize,
+    /// 지금 옮기는 문장의 자리와 어셈블리 줄마다의 자리
+    pos: Pos,
+    origins: Vec<Pos>,
+    out: String,
+}
+
+impl<'a> Gen<'a> {
+    fn new(program: &'a Program) -> Result<Self, Error> {
+        let functions: HashMap<&str, &Function> = program
+            .functions
+            .iter()
+            .map(|f| (f.name.as_str(), f))
+            .collect();
+
+        let mut scopes: Vec<(&str, Vec<&str>, &Block, usize)> = vec![];
+        let mut vars = vec![];
+        assigned(&program.main, &mut vars);
+        scopes.push(("", vars, &program.main, 1));
+        for f in &program.functions {
+            let mut vars: Vec<&str> = f.params.iter().map(String::as_str).collect();
+            assigned(&f.body, &mut vars);
+            scopes.push((&f.name, vars, &f.body, f.line));
+        }
+
+        let mut free = storages();
+        let mut slots = HashMap::new();
+        let mut graph: HashMap<&str, Vec<(&str, usize)>> = HashMap::new();
+        for (name, vars, body, line) in &scopes {
+            let mut calls = vec![];
+            for expr in exprs(body) {
+                check(expr, vars, &functions, &mut calls)?;
+            }
+            graph.insert(name, calls);
+
+            let mut map = HashMap::new();
+            for var in vars {
+                let slot = free.next().ok_or_else(|| Error {
+                    line: *line,
+                    message: format!(
+                        "too many variables, at most {} fit in storages",
+                        storages().count()
+                    ),
+                })?;
+                map.insert(*var, slot);
+            }
+            slots.insert(*name, map);
+        }
+
+        // 재귀는 펼칠 수 없다
+        fn visit<'g>(
+            name: &'g str,
+            graph: &HashMap<&'g str, Vec<(&'g str, usize)>>,
+            path: &mut Vec<&'g str>,
+        ) -> Result<(), Error> {
+            for &(callee, line) in &graph[name] {
+                if path.contains(&callee) {
+                    return Err(Error {
+                        line,
+                        message: format!("recursive call to {:?}", callee),
+                    });
+                }
+                path.push(callee);
+                visit(callee, graph, path)?;
+                path.pop();
+            }
+            Ok(())
+        }
+        for f in &program.functions {
+            visit(&f.name, &graph, &mut vec![f.name.as_str()])?;
+        }
+
+        Ok(Self {
+            functions,
+            slots,
+            scopes: vec![("", String::new())],
+            labels: 0,
+            pos: (1, 1),
+            origins: vec![],
+            out: String::new(),
+        })
+    }
+
+    fn emit(&mut self, line: &str) {
+        self.out += line;
+        self.out += "\n";
+        self.origins.push(self.pos);
+    }
+
+    fn label(&mut self, kind: &str) -> String {
+        self.labels += 1;
+        format!("{}_{}", kind, self.labels)
+    }
+
+    fn slot(&self, var: &str) -> char {
+        let scope = self.scopes.last().unwrap().0;
+        storage_name(self.slots[scope][var])
+    }
+
+    fn load(&mut self, var: &str) {
+        let slot = self.slot(var);
+        self.emit(&format!("sel {}", slot));
+        self.emit("dup");
+        self.emit(&format!("move {}", storage_name(EVAL)));
+        self.emit(&format!("sel {}", storage_name(EVAL)));
+    }
+
+    /// 셈 스택 맨 위의 값을 변수에
+    fn store(&mut self, var: &str) {
+        let slot = self.slot(var);
+        self.emit(&format!("sel {}", slot));
+        self.emit("pop");
+        self.emit(&format!("sel {}", storage_name(EVAL)));
+        self.emit(&format!("move {}", slot));
+    }
+
+    /// 0이면 1, 아니면 0
+    fn is_zero(&mut self) {
+        for op in [
+            "dup", "push 0", "cmp", "swap", "push 0", "swap", "cmp", "mul",
+        ] {
+            self.emit(op);
+        }
+    }
+
+    /// 0, 1을 뒤집는다
+    fn not(&mut self) {
+        for op in ["push 1", "swap", "sub"] {
+            self.emit(op);
+        }
+    }
+
+    fn truth(&mut self) {
+        self.is_zero();
+        self.not();
+    }
+
+    fn expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Num(n) => self.emit(&format!("push {}", n)),
+            Expr::Var(name, _) => self.load(name),
+            Expr::Call(name, args, _) => self.call(name, args),
+            Expr::Unary(op, e) => {
+                self.expr(e);
+                if *op == "-" {
+                    self.emit("push 0");
+                    self.emit("swap");
+                    self.emit("sub");
+                } else {
+                    self.is_zero();
+                }
+            }
+            Expr::Binary(op, l, r) => {
+                self.expr(l);
+                self.expr(r);
+                match *op {
+                    "+" => self.emit("add"),
+                    "-" => self.emit("sub"),
+                    "*" => self.emit("mul"),
+                    "/" => self.emit("div"),
+                    "%" => self.emit("mod"),
+                    ">=" => self.emit("cmp"),
+                    "<" => {
+                        self.emit("cmp");
+                        self.not();
+                    }
+                    "<=" => {
+                        self.emit("swap");
+                        self.emit("cmp");
+                    }
+                    ">" => {
+                        self.emit("swap");
+                        self.emit("cmp");
+                        self.not();
+                    }
+                    "==" => {
+                        self.emit("sub");
+                        self.is_zero();
+                    }
+                    "!=" => {
+                        self.emit("sub");
+                        self.truth();
+                    }
+                    _ => {
+                        self.truth();
+                        self.emit("swap");
+                        self.truth();
+                        if *op == "&&" {
+                            self.emit("mul");
+                        } else {
+                            self.emit("add");
+                            self.emit("push 1");
+                            self.emit("cmp");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// 인자를 셈 스택에 쌓고, 지역 변수를 0으로 되돌리고, 본문을 펼친다
+    fn call(&mut self, name: &str, args: &'a [Expr]) {
+        for arg in args {
+            self.expr(arg);
+        }
+
+        let function = self.functions[name];
+        let done = self.label(&format!("return_{}", name));
+        self.scopes.push((&function.name, done.clone()));
+
+        let mut locals: Vec<&str> = vec![];
+        assigned(&function.body, &mut locals);
+        for local in locals {
+            if !function.params.iter().any(|p| p == local) {
+                self.emit("push 0");
+                self.store(local);
+            }
+        }
+        for param in function.params.iter().rev() {
+            self.store(param);
+        }
+
+        self.block(&function.body);
+        self.emit("push 0");
+        self.emit(&format!("{}:", done));
+        self.scopes.pop();
+    }
+
+    fn block(&mut self, body: &'a [(Pos, Stmt)]) {
+        for (pos, stmt) in body {
+            // 펼친 함수에서 돌아오면 부른 문장의 자리로
+            let caller = self.pos;
+            self.pos = *pos;
+            self.stmt(stmt);
+            self.pos = caller;
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::Assign(name, e) => {
+                self.expr(e);
+                self.store(name);
+            }
+            Stmt::Print(e) => {
+                self.expr(e);
+                self.emit("print num");
+            }
+            Stmt::Printc(e) => {
+                self.expr(e);
+                self.emit("print char");
+            }
+            Stmt::Read(name) => {
+                self.emit("input num");
+                self.store(name);
+            }
+            Stmt::While(cond, body) => {
+                let start = self.label("while");
+                let end = self.label("end_while");
+                self.emit(&format!("{}:", start));
+                self.expr(cond);
+                self.emit(&format!("brz {}", end));
+                self.block(body);
+                self.emit(&format!("jmp {}", start));
+                self.emit(&format!("{}:", end));
+            }
+            Stmt::If(cond, then, other) => {
+                let other_label = self.label("else");
+                let end = self.label("end_if");
+                self.expr(cond);
+                self.emit(&format!("brz {}", other_label));
+                self.block(then);
+                if !other.is_empty() {
+                    self.emit(&format!("jmp {}", end));
+                }
+                self.emit(&format!("{}:", other_label));
+                if !other.is_empty() {
+                    self.block(other);
+                    self.emit(&format!("{}:", end));
+                }
+            }
+            Stmt::Return(e) => {
+                match e {
+                    Some(e) => self.expr(e),
+                    None => self.emit("push 0"),
+                }
+                let done = self.scopes.last().unwrap().1.clone();
+                self.emit(&format!("jmp {}", done));
+            }
+            Stmt::Expr(e) => {
+                self.expr(e);
+                self.emit("pop");
+            }
+        }
+    }
+}
+
+/// 어셈블리로 옮긴다. 변수는 저장공간마다 값 하나로 두고 처음에 0을 넣는다.
+/// 함수는 부른 자리에 펼치고 `&&`, `||`도 양쪽을 다 셈한다
+pub fn to_asm(src: &str) -> Result<String, Error> {
+    translate(src).map(|(asm, _)| asm)
+}
+
+/// 어셈블리와 그 줄마다 만든 문장의 자리
+fn translate(src: &str) -> Result<(String, Vec<Pos>), Error> {
+    let program = parse(src)?;
+    let mut g = Gen::new(&program)?;
+
+    let mut used: Vec<usize> = g.slots.values().flat_map(|m| m.values().copied()).collect();
+    used.sort();
+    for slot in used {
+        g.emit(&format!("sel {}", storage_name(slot)));
+        g.emit("push 0");
+    }
+    g.emit(&format!("sel {}", storage_name(EVAL)));
+    g.block(&program.main);
+    g.emit("halt");
+
+    Ok((g.out, g.origins))
+}
+
+/// 아희 격자로
+pub fn compile(src: &str) -> Result<String, Error> {
+    compile_with_map(src).map(|(grid, _)| grid)
+}
+
+/// `compile`과 같고, 셀마다 그 셀을 만든 문장의 자리를 함께
+pub fn compile_with_map(src: &str) -> Result<(String, SourceMap), Error> {
+    let (asm, origins) = translate(src)?;
+    let (grid, map) = assemble_with_map(&asm)?;
+    Ok((grid, map.compose(&origins)))
+}
+
+/// 기준 인터프리터가 멈춘 까닭
+enum Halt {
+    Return(i32),
+    DivideByZero,
+    Budget,
+}
+
+struct Machine<'a> {
+    functions: HashMap<&'a str, &'a Function>,
+    input: std::str::Lines<'a>,
+    out: String,
+    steps: usize,
+}
+
+impl<'a> Machine<'a> {
+    fn tick(&mut self) -> Result<(), Halt> {
+        if self.steps == 0 {
+            return Err(Halt::Budget);
+        }
+        self.steps -= 1;
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &'a Expr, env: &mut HashMap<&'a str, i32>) -> Result<i32, Halt> {
+        self.tick()?;
+        Ok(match expr {
+            Expr::Num(n) => *n,
+            Expr::Var(name, _) => env.get(name.as_str()).copied().unwrap_or(0),
+            Expr::Call(name, args, _) => {
+                let function = self.functions[name.as_str()];
+                let mut frame = HashMap::new();
+                for (param, arg) in function.params.iter().zip(args) {
+                    let value = self.expr(arg, env)?;
+                    frame.insert(param.as_str(), value);
+                }
+                match self.block(&function.body, &mut frame) {
+                    Ok(()) => 0,
+                    Err(Halt::Return(value)) => value,
+                    Err(halt) => return Err(halt),
+                }
+            }
+            Expr::Unary(op, e) => {
+                let v = self.expr(e, env)?;
+                match *op {
+                    "-" => v.wrapping_neg(),
+                    _ => (v == 0) as i32,
+                }
+            }
+            Expr::Binary(op, l, r) => {
+                let (a, b) = (self.expr(l, env)?, self.expr(r, env)?);
+                match *op {
+                    "+" => a.wrapping_add(b),
+                    "-" => a.wrapping_sub(b),
+                    "*" => a.wrapping_mul(b),
+                    "/" | "%" if b == 0 => return Err(Halt::DivideByZero),
+                    "/" => a.wrapping_div(b),
+                    "%" => a.wrapping_rem(b),
+                    "==" => (a == b) as i32,
+                    "!=" => (a != b) as i32,
+                    "<" => (a < b) as i32,
+                    "<=" => (a <= b) as i32,
+                    ">" => (a > b) as i32,
+                    ">=" => (a >= b) as i32,
+                    "&&" => (a != 0 && b != 0) as i32,
+                    _ => (a != 0 || b != 0) as i32,
+                }
+            }
+        })
+    }
+
+    fn block(
+        &mut self,
+        body: &'a [(Pos, Stmt)],
+        env: &mut HashMap<&'a str, i32>,
+    ) -> Result<(), Halt> {
+        for (_, stmt) in body {
+            self.tick()?;
+            match stmt {
+                Stmt::Assign(name, e) => {
+                    let v = self.expr(e, env)?;
+                    env.insert(name, v);
+                }
+                Stmt::Print(e) => {
+                    let v = self.expr(e, env)?;
+                    self.out += &v.to_string();
+                }
+                Stmt::Printc(e) => {
+                    let v = self.expr(e, env)?;
+                    self.out.push(char::from_u32(v as u32).unwrap_or(' '));
+                }
+                Stmt::Read(name) => {
+                    let v = self
+                        .input
+                        .next()
+                        .map_or(0, |l| l.trim().parse().unwrap_or(0));
+                    env.insert(name, v);
+                }
+                Stmt::While(cond, body) => {
+                    while self.expr(cond, env)? != 0 {
+                        self.block(body, env)?;
+                    }
+                }
+                Stmt::If(cond, then, other) => {
+                    if self.expr(cond, env)? != 0 {
+                        self.block(then, env)?;
+                    } else {
+                        self.block(other, env)?;
+                    }
+                }
+                Stmt::Return(e) => {
+                    let v = match e {
+                        Some(e) => self.expr(e, env)?,
+                        None => 0,
+                    };
+                    return Err(Halt::Return(v));
+                }
+                Stmt::Expr(e) => {
+                    self.expr(e, env)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 기준 인터프리터. 0으로 나누면 거기서 멈춘다. `steps` 안에 끝나지 않으면 `None`
+pub fn run(src: &str, input: &str, steps: usize) -> Result<Option<String>, Error> {
+    let program = parse(src)?;
+    // 모르는 이름과 재귀를 걸러낸다
+    Gen::new(&program)?;
+
+    let mut machine = Machine {
+        functions: program
+            .functions
+            .iter()
+            .map(|f| (f.name.as_str(), f))
+            .collect(),
+        input: input.lines(),
+        out: String::new(),
+        steps,
+    };
+    let halt = machine.block(&program.main, &mut HashMap::new());
+
+    Ok(match halt {
+        Err(Halt::Budget) => None,
+        _ => Some(machine.out),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aheui::{Aheui, ExitCode};
+    use crate::equiv;
+
+    /// 격자를 돌린 출력이 기준 인터프리터와 같은지. 끝난 방식을 돌려준다
+    fn agrees(src: &str, input: &str) -> Option<ExitCode> {
+        let expected = run(src, input, 1_000_000).unwrap().unwrap();
+        let grid = compile(src).unwrap();
+        let outcome = equiv::run(&Aheui::new(grid), input, 10_000_000);
+        assert_eq!(outcome.output, expected, "{}", src);
+        outcome.exit
+    }
+
+    #[test]
+    fn maps_the_print_cell_to_its_statement() {
+        let (grid, map) = compile_with_map("x = 6;\nx = x * 7;\n  print x;").unwrap();
+        let cell = crate::sourcemap::first_print(&grid, "");
+        assert_eq!(map.get(cell), Some((3, 3)));
+    }
+
+    #[test]
+    fn inlines_calls_inside_expressions() {
+        let src = "fn sq(x) { return x * x; }
+            fn add(a, b) { return a + b; }
+            print 1 + sq(3) * sq(2) - sq(sq(2)); printc ' ';
+            print add(sq(2), add(1, 2)) * 2;";
+        assert_eq!(run(src, "", 10_000).unwrap().unwrap(), "21 14");
+        agrees(src, "");
+    }
+
+    #[test]
+    fn returns_from_inside_loops() {
+        let src = "fn root(n) {
+                i = 0;
+                while 1 { if i * i >= n { return i; } i = i + 1; }
+            }
+            read n; print root(n); printc ' '; print root(n + 100);";
+        assert_eq!(run(src, "50\n", 10_000).unwrap().unwrap(), "8 13");
+        assert!(matches!(agrees(src, "50\n"), Some(ExitCode::Success(_))));
+    }
+
+    #[test]
+    fn divides_a_zero_dividend() {
+        let src = "x = 0; print 0 / 5; print x % 3; print -7 / 2; print -7 % 2;";
+        assert_eq!(run(src, "", 10_000).unwrap().unwrap(), "00-3-1");
+        agrees(src, "");
+    }
+
+    #[test]
+    fn stops_at_a_zero_divisor() {
+        for src in [
+            "print 1; print 7 / 0; print 2;",
+            "x = 0; print 1; print 7 % x; print 2;",
+        ] {
+            assert_eq!(run(src, "", 10_000).unwrap().unwrap(), "1");
+            assert_eq!(agrees(src, ""), Some(ExitCode::DivideByZero));
+        }
+    }
+
+    #[test]
+    fn rejects_too_many_variables() {
+        let src: String = (0..26).map(|i| format!("v{} = {};\n", i, i)).collect();
+        let err = compile(&src).unwrap_err();
+        assert!(err.message.starts_with("too many variables"), "{}", err);
+        assert!(run(&src, "", 10_000).is_err());
+
+        let src: String = (0..25).map(|i| format!("v{} = {};\n", i, i)).collect();
+        agrees(&src, "");
+    }
+
+    #[test]
+    fn rejects_recursion() {
+        for src in [
+            "fn f(n) { return f(n - 1); } print f(3);",
+            "fn f(n) { return g(n); }\nfn g(n) { return f(n); }\nprint f(1);",
+        ] {
+            let err = compile(src).unwrap_err();
+            assert!(err.message.starts_with("recursive call"), "{}", err);
+            assert!(run(src, "", 10_000).is_err());
+        }
+    }
+}
@@ -0,0 +1,27 @@
+pub mod aheui;
+pub mod aheuilize;
+pub mod analysis;
+pub mod asm;
+pub mod brainfuck;
+pub mod breakpoint;
+pub mod cfg;
+pub mod constant;
+pub mod cycle;
+pub mod decompile;
+pub mod disasm;
+pub mod equiv;
+pub mod evolve;
+pub mod golf;
+pub mod hangul;
+pub mod lang;
+pub mod link;
+pub mod literary;
+pub mod minify;
+pub mod normalize;
+pub mod raster;
+pub mod rng;
+pub mod sourcemap;
+pub mod stdlib;
+pub mod svg;
+pub mod symbolic;
+pub mod transform;
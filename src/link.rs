@@ -0,0 +1,308 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+use crate::aheui::Aheui;
+use crate::analysis::{State, required_depth};
+use crate::disasm::out_dir;
+use crate::hangul::{FILLER, get_end_count, storage_name};
+
+/// 링크할 격자 하나
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name: String,
+    pub src: String,
+}
+
+/// 모듈이 건드리는 저장공간
+#[derive(Debug, Clone)]
+pub struct Usage {
+    pub name: String,
+    pub storages: BTreeSet<usize>,
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self
+            .storages
+            .iter()
+            .map(|&idx| storage_name(idx).to_string())
+            .collect();
+        write!(f, "{}: {}", self.name, names.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub module: String,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.module, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// 모듈의 흐름을 따라가며 건드리는 저장공간과 아래 가장자리로 나가는지 본다.
+/// 저장공간의 값은 앞 모듈이 남긴 것일 수 있으므로, 실패할 수 있는 명령은
+/// 통과와 반사를 모두 따라간다. 값이 모자라 반사된 뒤의 길은 모듈이 바라는
+/// 조건을 어긴 것일 수도 있으니 다른 가장자리로 나가도 오류로 보지 않는다
+pub(crate) fn explore(aheui: &Aheui) -> Result<(BTreeSet<usize>, bool), String> {
+    let (w, h) = (aheui.src_mat.0 as i32, aheui.src_mat.1 as i32);
+    let mut storages = BTreeSet::new();
+    let mut exits = false;
+
+    let start = State {
+        cursor: (0, 0),
+        dir: (0, 1),
+        sel: 0,
+    };
+    // (상태, 모자라서 반사된 적이 있는지)
+    let mut seen = HashSet::from([(start, false)]);
+    let mut stack = vec![(start, false)];
+    while let Some((State { cursor, dir, sel }, underflowed)) = stack.pop() {
+        let chr = aheui.src_map[cursor.1 as usize][cursor.0 as usize];
+
+        let touches = required_depth(&chr).is_some() || matches!(chr.0, 'ㅂ' | 'ㅎ');
+        if touches {
+            storages.insert(sel);
+        }
+        let next_sel = match chr.0 {
+            'ㅅ' => get_end_count(chr.2),
+            _ => sel,
+        };
+        if chr.0 == 'ㅆ' {
+            storages.insert(get_end_count(chr.2));
+        }
+        if matches!(chr.0, 'ㅅ' | 'ㅆ') && get_end_count(chr.2) >= 27 {
+            return Err(format!(
+                "{} at ({}, {}) names the ㅎ storage",
+                chr.3, cursor.0, cursor.1
+            ));
+        }
+        if chr.0 == 'ㅎ' {
+            continue;
+        }
+
+        let out = out_dir(chr, dir);
+        let mut dirs = vec![(out, underflowed)];
+        if chr.0 != ' ' && required_depth(&chr).is_some() {
+            // 0을 꺼낸 `ㅊ`은 모자란 것이 아니다
+            dirs.push(((-out.0, -out.1), underflowed || chr.0 != 'ㅊ'));
+        }
+
+        for (dir, underflowed) in dirs {
+            let (x, y) = (cursor.0 + dir.0, cursor.1 + dir.1);
+            if (0..w).contains(&x) && (0..h).contains(&y) {
+                let next = State {
+                    cursor: (x, y),
+                    dir,
+                    sel: next_sel,
+                };
+                if seen.insert((next, underflowed)) {
+                    stack.push((next, underflowed));
+                }
+            } else if y >= h {
+                exits = true;
+            } else if !underflowed {
+                let edge = if y < 0 {
+                    "top"
+                } else if x < 0 {
+                    "left"
+                } else {
+                    "right"
+                };
+                return Err(format!(
+                    "leaves through the {} edge at ({}, {}); only the bottom edge is an exit",
+                    edge, cursor.0, cursor.1
+                ));
+            }
+        }
+    }
+
+    Ok((storages, exits))
+}
+
+/// 모듈을 위에서 아래로 쌓는다.
+///
+/// - 모듈은 혼자 실행될 때처럼 (0, 0)에서 아래로 들어가며 `아`가 선택되어 있다
+/// - 마지막 행 아래로 내려가면 끝나고 다음 모듈로 간다. 다른 가장자리로 나가면
+///   감싸는 곳이 달라지므로 오류
+/// - 모듈 사이의 두 행이 어느 열에서 내려오든 x = 0으로 모아 `아`를 고른다.
+///   속도 2로 떨어져도 둘째 행이 받는다. 마지막 모듈 아래는 끝내는 두 행
+///
+/// `shared`에 없는 저장공간을 두 모듈이 건드리면 오류
+pub fn link(modules: &[Module], shared: &[usize]) -> Result<(String, Vec<Usage>), Error> {
+    let mut parsed = vec![];
+    let mut usages: Vec<Usage> = vec![];
+
+    for (idx, module) in modules.iter().enumerate() {
+        let err = |message: String| Error {
+            module: module.name.clone(),
+            message,
+        };
+        let aheui = Aheui::new(module.src.clone());
+        if aheui.src_mat.0 == 0 || aheui.src_mat.1 == 0 {
+            return Err(err("empty module".to_string()));
+        }
+
+        let (storages, exits) = explore(&aheui).map_err(err)?;
+        if !exits && idx + 1 < modules.len() {
+            return Err(err(
+                "never reaches its bottom edge, so later modules cannot run".to_string(),
+            ));
+        }
+        for usage in &usages {
+            let clash = storages
+                .intersection(&usage.storages)
+                .find(|idx| !shared.contains(idx));
+            if let Some(&clash) = clash {
+                return Err(err(format!(
+                    "also uses {} of {}; pass it as shared if this is intended",
+                    storage_name(clash),
+                    usage.name
+                )));
+            }
+        }
+
+        usages.push(Usage {
+            name: module.name.clone(),
+            storages,
+        });
+        parsed.push(aheui);
+    }
+
+    let width = parsed.iter().map(|a| a.src_mat.0).max().unwrap_or(1);
+    let row = |first: char, rest: char| -> String {
+        std::iter::once(first)
+            .chain(std::iter::repeat_n(rest, width - 1))
+            .collect::<String>()
+            + "\n"
+    };
+
+    let mut out = String::new();
+    for (idx, aheui) in parsed.iter().enumerate() {
+        if idx > 0 {
+            out += &row('우', '어');
+            out += &row('수', '어');
+        }
+        for cells in &aheui.src_map {
+            let line: String = cells.iter().map(|c| c.3).collect();
+            out += &line;
+            out.extend(std::iter::repeat_n(FILLER, width - cells.len()));
+            out += "\n";
+        }
+    }
+    out += &row('후', '후');
+    out += &row('후', '후');
+
+    Ok((out, usages))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aheui::ExitCode;
+    use crate::equiv::run;
+
+    fn module(name: &str, src: &str) -> Module {
+        Module {
+            name: name.to_string(),
+            src: src.to_string(),
+        }
+    }
+
+    fn explore_src(src: &str) -> Result<(BTreeSet<usize>, bool), String> {
+        explore(&Aheui::new(src.to_string()))
+    }
+
+    fn message(modules: &[Module]) -> String {
+        let err = link(modules, &[]).unwrap_err();
+        assert_eq!(err.module, modules.last().unwrap().name);
+        err.message
+    }
+
+    // 2를 출력하고 ㅠ로 두 칸 떨어져 나간다
+    const PRINT_2: &str = "분\n뮹\n";
+    // ㄷ 저장공간에 5를 넣고 출력한 뒤 끝낸다
+    const PRINT_5: &str = "삳받반다망희\n";
+
+    #[test]
+    fn explores_storages_and_the_exit() {
+        assert_eq!(explore_src(PRINT_2), Ok((BTreeSet::from([0]), true)));
+        assert_eq!(explore_src(PRINT_5), Ok((BTreeSet::from([7]), false)));
+        // ㅆ은 옮겨 넣는 곳도 건드린다
+        assert_eq!(explore_src("쑫\n"), Ok((BTreeSet::from([0, 7]), true)));
+        // 모자라서 위로 반사되는 길은 앞 모듈이 값을 남기지 않은 경우뿐이다
+        assert_eq!(explore_src("뭉\n"), Ok((BTreeSet::from([0]), true)));
+    }
+
+    #[test]
+    fn stacks_modules_of_different_widths() {
+        let modules = [module("two", PRINT_2), module("five", PRINT_5)];
+        let (out, usages) = link(&modules, &[]).unwrap();
+        assert_eq!(
+            out,
+            "분ㅇㅇㅇㅇㅇ\n뮹ㅇㅇㅇㅇㅇ\n우어어어어어\n수어어어어어\n삳받반다망희\n후후후후후후\n후후후후후후\n"
+        );
+        assert_eq!(usages[1].to_string(), "five: 앋");
+
+        let outcome = run(&Aheui::new(out), "", 1000);
+        assert_eq!(outcome.output, "25");
+        assert_eq!(outcome.exit, Some(ExitCode::Success(0)));
+    }
+
+    #[test]
+    fn catches_a_speed_2_drop_in_either_lane() {
+        // 마지막 행에서 두 칸 떨어지면 수 행이, 한 칸이나 그 위에서 두 칸이면 우 행이 받는다
+        for first in ["분\n뮹\n", "분\n뭉\n", "분\n뮹\nㅇ\n"] {
+            let modules = [module("first", first), module("five", PRINT_5)];
+            let (out, _) = link(&modules, &[]).unwrap();
+            assert_eq!(run(&Aheui::new(out), "", 1000).output, "25", "{}", first);
+        }
+    }
+
+    #[test]
+    fn rejects_storage_clashes_unless_shared() {
+        let modules = [module("two", PRINT_2), module("again", PRINT_2)];
+        assert_eq!(
+            message(&modules),
+            "also uses 아 of two; pass it as shared if this is intended"
+        );
+        let (out, _) = link(&modules, &[0]).unwrap();
+        assert_eq!(run(&Aheui::new(out), "", 1000).output, "22");
+    }
+
+    #[test]
+    fn rejects_other_edges() {
+        for (src, edge) in [("오\n", "top"), ("어\n", "left"), ("아\n", "right")] {
+            assert_eq!(
+                message(&[module("m", src)]),
+                format!(
+                    "leaves through the {} edge at (0, 0); only the bottom edge is an exit",
+                    edge
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_a_module_that_never_ends_below() {
+        let modules = [module("five", PRINT_5), module("two", PRINT_2)];
+        let err = link(&modules, &[]).unwrap_err();
+        assert_eq!(err.module, "five");
+        assert_eq!(
+            err.message,
+            "never reaches its bottom edge, so later modules cannot run"
+        );
+        // 마지막 모듈은 끝내기만 해도 된다
+        assert!(link(&modules[..1], &[]).is_ok());
+        assert_eq!(message(&[module("empty", "\n")]), "empty module");
+        assert_eq!(
+            message(&[module("heut", "샇\n")]),
+            "샇 at (0, 0) names the ㅎ storage"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use crate::hangul::{KChar, assemble_jamo, disassemble_jamo, jamo::*};
+use crate::normalize::{canonical, is_direction};
+use crate::rng::Rng;
+
+/// 기본 낱말 목록
+#[rustfmt::skip]
+pub const WORDS: &[&str] = &[
+    "아버지", "어머니", "사랑", "하늘", "바다", "나무", "사람", "마음", "노래", "바람",
+    "구름", "달빛", "별빛", "꽃잎", "눈물", "아침", "저녁", "한낮", "봄날", "여름",
+    "가을", "겨울", "머리", "가슴", "하나", "다섯", "우리", "그대", "그녀", "고양이",
+    "강아지", "사과", "떡국", "김치", "두부", "바닷가", "파도", "모래", "등불", "촛불",
+    "창문", "편지", "그림자", "발자국", "시간", "세월", "내일", "오늘", "어제", "처음",
+    "마지막", "시작", "사이", "너머", "아래", "옆집", "마을", "고향", "나라", "동무",
+    "친구", "아이", "어른", "소리", "냄새", "빗소리", "새벽", "한밤", "들판", "언덕",
+    "골목", "시장", "학교", "선생", "학생", "글자", "이야기", "노을", "햇살", "바위",
+    "있다", "없다", "하다", "되다", "보다", "오다", "가다", "주다", "받다", "먹다",
+    "자다", "살다", "알다", "모르다", "좋다", "싫다", "크다", "작다", "많다", "적다",
+    "높다", "낮다", "멀다", "가깝다", "곱다", "밝다", "어둡다", "슬프다", "기쁘다", "웃다",
+    "울다", "걷다", "뛰다", "날다", "부르다", "흐르다", "피다", "지다", "그리다", "기다리다",
+    "그리고", "그러나", "하지만", "그래서", "다시", "아직", "이미", "벌써", "모두", "함께",
+    "혼자", "천천히", "빨리", "조용히", "가득", "너무", "아주", "정말", "어디", "언제",
+    "누구", "무엇", "어떻게", "에서", "까지", "부터", "처럼", "보다", "마저", "조차",
+    "한다", "했다", "하네", "하며", "하고", "하니", "보니", "가니", "오니", "노라",
+    "로다", "구나", "도다", "더라", "리라", "세요", "니다", "습니다", "입니다", "이다",
+    "밤", "낮", "봄", "길", "집", "문", "손", "발", "눈", "코", "입", "꿈", "잠", "숨",
+    "빛", "끝", "위", "앞", "뒤", "속", "밖", "안", "물", "불", "돌", "산", "강", "들",
+    "숲", "새", "소", "개", "밥", "떡", "국", "술", "차", "빵", "배", "감", "귤", "해",
+    "달", "별", "꽃", "비", "말", "일", "날", "때", "곳", "것", "수", "나", "너", "그",
+    "은", "는", "이", "가", "을", "를", "에", "로", "와", "과", "도", "만", "의", "요",
+];
+
+/// 셀 하나가 하는 일. 같으면 서로 바꿔 써도 된다
+fn class(chr: KChar) -> char {
+    if chr.0 == ' ' {
+        // 한글이 아닌 글자는 방향을 바꾸지 않는 ㅇ과 같다
+        '애'
+    } else {
+        canonical(chr)
+    }
+}
+
+/// 대표 음절마다 같은 일을 하는 모든 음절
+fn classes() -> HashMap<char, Vec<char>> {
+    let mut map: HashMap<char, Vec<char>> = HashMap::new();
+
+    for start in _START {
+        for middle in _MIDDLE {
+            for end in _END {
+                let chr = assemble_jamo(start, middle, end);
+                map.entry(class(disassemble_jamo(chr)))
+                    .or_default()
+                    .push(chr);
+            }
+        }
+    }
+
+    map
+}
+
+/// 각 셀을 같은 일을 하는 음절로 바꾸되, 가능하면 `words`의 낱말로 채운다.
+/// 방향을 바꾸지 않고 아무 일도 하지 않는 셀은 띄어쓰기가 될 수 있다
+pub fn literary(src: &str, words: &[&str], seed: u64) -> String {
+    let classes = classes();
+    let mut rng = Rng::new(seed);
+
+    // (글자, 각 글자가 하는 일)
+    let words: Vec<(Vec<char>, Vec<char>)> = words
+        .iter()
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let chars: Vec<char> = w.chars().collect();
+            let kinds = chars.iter().map(|&c| class(disassemble_jamo(c))).collect();
+            (chars, kinds)
+        })
+        .collect();
+
+    src.lines()
+        .map(|line| {
+            let cells: Vec<char> = line.chars().map(|c| class(disassemble_jamo(c))).collect();
+            let mut out = String::new();
+            let mut x = 0;
+
+            while x < cells.len() {
+                let fits: Vec<&Vec<char>> = words
+                    .iter()
+                    .filter(|(_, kinds)| cells[x..].starts_with(kinds))
+                    .map(|(chars, _)| chars)
+                    .collect();
+
+                // 긴 낱말을 먼저
+                let longest = fits.iter().map(|w| w.len()).max().unwrap_or(0);
+                let fits: Vec<&Vec<char>> = fits
+                    .into_iter()
+                    .filter(|w| w.len() + 1 >= longest)
+                    .collect();
+
+                if let Some(word) = rng.pick(&fits) {
+                    out.extend(word.iter());
+                    x += word.len();
+                } else {
+                    out.push(filler(cells[x], &classes, &mut rng));
+                    x += 1;
+                }
+            }
+
+            out + "\n"
+        })
+        .collect()
+}
+
+/// 낱말이 맞지 않는 셀. 아무 일도 하지 않으면 띄어쓰기
+fn filler(cell: char, classes: &HashMap<char, Vec<char>>, rng: &mut Rng) -> char {
+    if cell == '애' {
+        return ' ';
+    }
+
+    let Some(same) = classes.get(&cell) else {
+        return cell;
+    };
+
+    // 겹받침이나 쓰지 않는 홀소리가 적은 음절이 더 자연스럽다
+    let plain: Vec<char> = same
+        .iter()
+        .copied()
+        .filter(|&c| {
+            let k = disassemble_jamo(c);
+            k.2.1 == ' ' && (is_direction(k.1) || matches!(k.1, 'ㅐ' | 'ㅔ' | 'ㅚ' | 'ㅟ'))
+        })
+        .collect();
+
+    match rng.pick(&plain) {
+        Some(&c) => c,
+        None => *rng.pick(same).unwrap_or(&cell),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equiv::{SAMPLES, assert_equivalent};
+
+    #[test]
+    fn keeps_the_behavior() {
+        for src in SAMPLES {
+            for seed in [0, 7] {
+                assert_equivalent(src, &literary(src, WORDS, seed));
+            }
+        }
+    }
+
+    #[test]
+    fn uses_the_given_words() {
+        let out = literary("밤밤밤밤희\n", &["밤"], 0);
+        assert!(out.starts_with("밤밤밤밤"), "{}", out);
+    }
+}
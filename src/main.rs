@@ -1,30 +1,257 @@
 use std::fs;
+use std::path::PathBuf;
 
-use clap::Parser;
-use color_eyre::Result;
+use clap::{Parser, Subcommand};
+use color_eyre::{Result, eyre::eyre};
 
-mod aheui;
-mod hangul;
-
-use aheui::Aheui;
+use oxidaheuize::aheui::{Aheui, ExitCode};
+use oxidaheuize::aheuilize::{Layout, Style, aheuilize_with_map};
+use oxidaheuize::analysis::{DepthAnalysis, Verdict};
+use oxidaheuize::asm::{self, assemble_with_map};
+use oxidaheuize::brainfuck;
+use oxidaheuize::breakpoint::{self, Breakpoint};
+use oxidaheuize::cfg::{self, Format};
+use oxidaheuize::constant::{self, Cost, Synthesizer};
+use oxidaheuize::decompile::decompile;
+use oxidaheuize::disasm::{self, disassemble};
+use oxidaheuize::equiv::{self, Bounds};
+use oxidaheuize::evolve::{self, Settings};
+use oxidaheuize::golf::{self, Case};
+use oxidaheuize::hangul::{disassemble_jamo, storage_name};
+use oxidaheuize::lang;
+use oxidaheuize::link::{Module, link};
+use oxidaheuize::literary::{self, literary};
+use oxidaheuize::minify::minify;
+use oxidaheuize::normalize::normalize;
+use oxidaheuize::raster::{self, Mode};
+use oxidaheuize::sourcemap::SourceMap;
+use oxidaheuize::stdlib;
+use oxidaheuize::svg;
+use oxidaheuize::symbolic::{self, Limits};
+use oxidaheuize::transform::{Transform, transform};
 
 fn main() -> Result<()> {
     color_eyre::install()?;
 
     let args = Args::parse();
 
-    let contents = fs::read_to_string(args.file).expect("Failed to read file.");
+    match args.command {
+        Some(Command::Lint { file, all }) => lint(file, all),
+        Some(Command::Reach {
+            file,
+            x,
+            y,
+            steps,
+            output,
+        }) => reach(file, (x, y), steps, output),
+        Some(Command::Minify {
+            file,
+            filler,
+            verify,
+        }) => minify_file(file, filler, verify),
+        Some(Command::Normalize { file, check }) => normalize_file(file, check),
+        Some(Command::Literary { file, words, seed }) => literary_file(file, words, seed),
+        Some(Command::Transform {
+            transform,
+            file,
+            verify,
+        }) => transform_file(file, transform, verify),
+        Some(Command::Aheuilize {
+            file,
+            width,
+            wrap_lines,
+            style,
+            verify,
+            source_map,
+        }) => aheuilize_file(
+            file,
+            Layout {
+                width,
+                wrap_lines,
+                style,
+            },
+            verify,
+            source_map,
+        ),
+        Some(Command::Asm {
+            file,
+            output,
+            source_map,
+            module,
+        }) => asm(file, output, source_map, module),
+        Some(Command::Bf {
+            file,
+            output,
+            asm,
+            verify,
+            input,
+            steps,
+            source_map,
+        }) => bf(file, output, asm, verify, input, steps, source_map),
+        Some(Command::Compile {
+            file,
+            output,
+            asm,
+            verify,
+            input,
+            steps,
+            source_map,
+        }) => compile(file, output, asm, verify, input, steps, source_map),
+        Some(Command::Link {
+            modules,
+            output,
+            shared,
+        }) => link_files(modules, output, shared),
+        Some(Command::Stdlib { name, output, asm }) => stdlib_routine(name, output, asm),
+        Some(Command::Disasm { file }) => disasm(file),
+        Some(Command::Cfg { file, format }) => {
+            let aheui = Aheui::new(read_source(file));
+            print!("{}", cfg::render(&aheui, &disassemble(&aheui), format));
+            Ok(())
+        }
+        Some(Command::Svg {
+            file,
+            trace,
+            input,
+            steps,
+            output,
+        }) => svg_file(file, trace, input, steps, output),
+        Some(Command::Image {
+            file,
+            output,
+            mode,
+            scale,
+            input,
+            steps,
+        }) => image(file, output, mode, scale, input, steps),
+        Some(Command::Decompile { file }) => {
+            print!("{}", decompile(&Aheui::new(read_source(file))));
+            Ok(())
+        }
+        Some(Command::Constant {
+            numbers,
+            push_cost,
+            arith_cost,
+            dup_cost,
+            swap_cost,
+            verify,
+        }) => constant(
+            numbers,
+            Cost {
+                push: push_cost,
+                arith: arith_cost,
+                dup: dup_cost,
+                swap: swap_cost,
+            },
+            verify,
+        ),
+        Some(Command::Equiv {
+            left,
+            right,
+            lines,
+            steps,
+            random,
+            seed,
+        }) => equiv(
+            left,
+            right,
+            Bounds {
+                lines,
+                steps,
+                random,
+                seed,
+            },
+        ),
+        Some(Command::Evolve {
+            target,
+            input,
+            population,
+            generations,
+            steps,
+            width,
+            height,
+            seed,
+            output,
+        }) => evolve_target(
+            target,
+            input,
+            Settings {
+                population,
+                generations,
+                steps,
+                width,
+                height,
+                seed,
+            },
+            output,
+        ),
+        Some(Command::Golf {
+            file,
+            cases,
+            steps,
+            output,
+        }) => golf_file(file, cases, steps, output),
+        None => run(
+            args.file.expect("file is required"),
+            args.debug,
+            args.detect_loops,
+            args.source_map,
+            args.breakpoints,
+        ),
+    }
+}
+
+fn read_source(file: PathBuf) -> String {
+    fs::read_to_string(file).expect("Failed to read file.")
+}
 
-    let mut parser = Aheui::new(contents);
+fn run(
+    file: PathBuf,
+    debug: bool,
+    detect_loops: Option<usize>,
+    source_map: Option<PathBuf>,
+    breakpoints: Vec<Breakpoint>,
+) -> Result<()> {
+    // 따로 주지 않으면 옆에 있는 `<file>.map`
+    let source_map = source_map.or_else(|| {
+        let mut path = file.clone().into_os_string();
+        path.push(".map");
+        Some(PathBuf::from(path)).filter(|p| p.exists())
+    });
+    let source_map = match source_map {
+        Some(path) => Some(SourceMap::parse(&fs::read_to_string(path)?).map_err(|e| eyre!(e))?),
+        None => None,
+    };
 
-    parser.debug = args.debug;
+    let mut parser = Aheui::new(read_source(file));
+    parser.detect_loops(detect_loops);
+    parser.set_source_map(source_map);
 
     // println!("{:?}", parser.src_mat);
 
     loop {
+        let stop = breakpoint::check(&parser, &breakpoints, |message| eprintln!("{}", message));
+        if let Some(idx) = stop {
+            eprintln!(
+                "\nbreakpoint {} ({}) after step {} at ({}, {})",
+                idx + 1,
+                breakpoints[idx],
+                parser.step,
+                parser.cursor.0,
+                parser.cursor.1
+            );
+            for (idx, items) in parser.stacks.iter().enumerate() {
+                if !items.is_empty() || idx == parser.sel {
+                    let mark = if idx == parser.sel { ">" } else { " " };
+                    eprintln!("{}{}: {:?}", mark, storage_name(idx), items);
+                }
+            }
+            break;
+        }
+
         parser.next();
 
-        if args.debug {
+        if debug {
             parser.print_state();
         }
 
@@ -36,13 +263,971 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+fn lint(file: PathBuf, all: bool) -> Result<()> {
+    let aheui = Aheui::new(read_source(file));
+    let analysis = DepthAnalysis::run(&aheui);
+
+    for finding in analysis.findings(&aheui) {
+        let message = match finding.verdict {
+            Verdict::Fail => "always reflects",
+            Verdict::Pass if all => "always passes",
+            Verdict::Unknown if all => "depends on storage depth",
+            _ => continue,
+        };
+        let storages: String = finding.sel.iter().map(|&s| storage_name(s)).collect();
+
+        println!(
+            "({}, {}) {}: {} [{}]",
+            finding.cursor.0, finding.cursor.1, finding.chr, message, storages
+        );
+    }
+
+    Ok(())
+}
+
+fn reach(file: PathBuf, target: (i32, i32), steps: usize, output: Option<PathBuf>) -> Result<()> {
+    let aheui = Aheui::new(read_source(file));
+    let limits = Limits {
+        steps,
+        ..Default::default()
+    };
+
+    match symbolic::find_input(&aheui, target, limits) {
+        Some(input) => match output {
+            Some(path) => fs::write(path, input)?,
+            None => print!("{}", input),
+        },
+        None => {
+            return Err(eyre!(
+                "no input reaches ({}, {}) within {} steps",
+                target.0,
+                target.1,
+                steps
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn write_source_map(path: Option<PathBuf>, map: &SourceMap) -> Result<()> {
+    if let Some(path) = path {
+        fs::write(path, map.to_string())?;
+    }
+    Ok(())
+}
+
+fn bf(
+    file: PathBuf,
+    output: Option<PathBuf>,
+    asm: bool,
+    verify: bool,
+    input: Option<PathBuf>,
+    steps: usize,
+    source_map: Option<PathBuf>,
+) -> Result<()> {
+    let src = read_source(file);
+    let code = if asm {
+        brainfuck::to_asm(&src)?
+    } else {
+        let (grid, map) = brainfuck::compile_with_map(&src)?;
+        write_source_map(source_map, &map)?;
+        grid
+    };
+
+    if verify && !asm {
+        let input = input
+            .map(fs::read_to_string)
+            .transpose()?
+            .unwrap_or_default();
+        let expected = brainfuck::run(&src, &input, steps)?
+            .ok_or_else(|| eyre!("Brainfuck did not finish within {} steps", steps))?;
+        // 명령 하나가 여러 셀이고 점프는 길을 따라 돌아간다
+        let outcome = equiv::run(
+            &Aheui::new(code.clone()),
+            &input,
+            steps.saturating_mul(1000),
+        );
+        if outcome.exit.is_none() {
+            return Err(eyre!("compiled grid did not finish"));
+        }
+        if outcome.output != expected {
+            return Err(eyre!(
+                "compiled grid printed {:?} but Brainfuck printed {:?}",
+                outcome.output,
+                expected
+            ));
+        }
+    }
+
+    match output {
+        Some(path) => fs::write(path, code)?,
+        None => print!("{}", code),
+    }
+
+    Ok(())
+}
+
+fn compile(
+    file: PathBuf,
+    output: Option<PathBuf>,
+    asm: bool,
+    verify: bool,
+    input: Option<PathBuf>,
+    steps: usize,
+    source_map: Option<PathBuf>,
+) -> Result<()> {
+    let src = read_source(file);
+    let code = if asm {
+        lang::to_asm(&src)?
+    } else {
+        let (grid, map) = lang::compile_with_map(&src)?;
+        write_source_map(source_map, &map)?;
+        grid
+    };
+
+    if verify && !asm {
+        let input = input
+            .map(fs::read_to_string)
+            .transpose()?
+            .unwrap_or_default();
+        let expected = lang::run(&src, &input, steps)?
+            .ok_or_else(|| eyre!("program did not finish within {} steps", steps))?;
+        let outcome = equiv::run(
+            &Aheui::new(code.clone()),
+            &input,
+            steps.saturating_mul(1000),
+        );
+        if outcome.exit.is_none() {
+            return Err(eyre!("compiled grid did not finish"));
+        }
+        if outcome.output != expected {
+            return Err(eyre!(
+                "compiled grid printed {:?} but the program printed {:?}",
+                outcome.output,
+                expected
+            ));
+        }
+    }
+
+    match output {
+        Some(path) => fs::write(path, code)?,
+        None => print!("{}", code),
+    }
+
+    Ok(())
+}
+
+fn link_files(modules: Vec<PathBuf>, output: Option<PathBuf>, shared: Vec<String>) -> Result<()> {
+    let shared = shared
+        .iter()
+        .map(|s| asm::storage(s).ok_or_else(|| eyre!("unknown storage {:?}", s)))
+        .collect::<Result<Vec<_>>>()?;
+    let modules = modules
+        .into_iter()
+        .map(|path| {
+            let name = path.display().to_string();
+            // `std:이름`은 표준 라이브러리의 루틴
+            let src = match name.strip_prefix("std:") {
+                Some(routine) => {
+                    let routine = stdlib::find(routine)
+                        .ok_or_else(|| eyre!("no routine {:?} in the standard library", routine))?;
+                    routine.module()?.0
+                }
+                None => read_source(path),
+            };
+            Ok(Module { name, src })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (program, usages) = link(&modules, &shared)?;
+    for usage in usages {
+        eprintln!("{}", usage);
+    }
+
+    match output {
+        Some(path) => fs::write(path, program)?,
+        None => print!("{}", program),
+    }
+
+    Ok(())
+}
+
+fn stdlib_routine(name: Option<String>, output: Option<PathBuf>, asm: bool) -> Result<()> {
+    let Some(name) = name else {
+        println!("standard library v{}", stdlib::VERSION);
+        for routine in stdlib::ROUTINES {
+            routine
+                .check()
+                .map_err(|err| eyre!("{}: {}", routine.name, err))?;
+            println!("{}", routine);
+        }
+        return Ok(());
+    };
+
+    let routine = stdlib::find(&name)
+        .ok_or_else(|| eyre!("no routine {:?} in the standard library", name))?;
+    let text = match asm {
+        true => routine.src.to_string(),
+        false => routine.module()?.0,
+    };
+
+    match output {
+        Some(path) => fs::write(path, text)?,
+        None => print!("{}", text),
+    }
+
+    Ok(())
+}
+
+fn disasm(file: PathBuf) -> Result<()> {
+    let aheui = Aheui::new(read_source(file));
+    let runs = disassemble(&aheui);
+
+    print!("{}", disasm::render(&aheui, &runs));
+
+    Ok(())
+}
+
+fn equiv(left: PathBuf, right: PathBuf, bounds: Bounds) -> Result<()> {
+    let left = Aheui::new(read_source(left));
+    let right = Aheui::new(read_source(right));
+
+    let (tried, found) = equiv::check(&left, &right, bounds);
+
+    match found {
+        None => println!("no difference found in {} inputs", tried),
+        Some(found) => {
+            println!("programs differ on input {:?}", found.input);
+            println!("  left:  {:?} {:?}", found.left.output, found.left.exit);
+            println!("  right: {:?} {:?}", found.right.output, found.right.exit);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+fn evolve_target(
+    target: String,
+    input: Option<PathBuf>,
+    settings: Settings,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    if settings.width == 0 || settings.height == 0 {
+        return Err(eyre!("width and height must be at least 1"));
+    }
+    let input = input
+        .map(fs::read_to_string)
+        .transpose()?
+        .unwrap_or_default();
+
+    let (best, generation) = evolve::evolve(&target, &input, settings, |generation, found| {
+        eprintln!(
+            "generation {}: distance {}, {}x{}{}",
+            generation,
+            found.distance,
+            found.grid.first().map_or(0, Vec::len),
+            found.grid.len(),
+            if found.ended { "" } else { ", does not end" }
+        );
+    });
+
+    if best.distance == 0 && best.ended {
+        eprintln!("found in generation {}", generation);
+    } else {
+        eprintln!(
+            "closest output {:?} in generation {}",
+            best.output, generation
+        );
+    }
+
+    match output {
+        Some(path) => fs::write(path, best.src())?,
+        None => print!("{}", best.src()),
+    }
+
+    Ok(())
+}
+
+fn golf_file(
+    file: PathBuf,
+    cases: Vec<PathBuf>,
+    steps: usize,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    // 따로 주지 않으면 옆에 있는 `<file>.out`
+    let cases = match cases.is_empty() {
+        true => vec![file.with_extension("out")],
+        false => cases,
+    };
+    let cases = cases
+        .into_iter()
+        .map(|out| {
+            let input = out.with_extension("in");
+            Ok(Case {
+                name: out.display().to_string(),
+                input: match input.exists() {
+                    true => fs::read_to_string(input)?,
+                    false => String::new(),
+                },
+                output: fs::read_to_string(&out)
+                    .map_err(|err| eyre!("{}: {}", out.display(), err))?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let src = read_source(file);
+    let golfed = golf::golf(&src, &cases, steps)
+        .map_err(|name| eyre!("the original program already fails {}", name))?;
+    eprintln!("{} -> {} characters", golf::size(&src), golf::size(&golfed));
+
+    match output {
+        Some(path) => fs::write(path, golfed)?,
+        None => print!("{}", golfed),
+    }
+
+    Ok(())
+}
+
+fn minify_file(file: PathBuf, filler: char, verify: bool) -> Result<()> {
+    if disassemble_jamo(filler).0 != ' ' {
+        return Err(eyre!("filler {:?} must not be a Hangul syllable", filler));
+    }
+
+    let aheui = Aheui::new(read_source(file));
+    let minified = minify(&aheui, filler);
+
+    if verify {
+        let (_, found) = equiv::check(&aheui, &Aheui::new(minified.clone()), Bounds::default());
+        if let Some(found) = found {
+            return Err(eyre!("minified program differs on input {:?}", found.input));
+        }
+    }
+
+    print!("{}", minified);
+
+    Ok(())
+}
+
+fn normalize_file(file: PathBuf, check: bool) -> Result<()> {
+    let src = read_source(file);
+    let normalized = normalize(&src);
+
+    if check {
+        if normalized != src {
+            return Err(eyre!("source is not normalized"));
+        }
+    } else {
+        print!("{}", normalized);
+    }
+
+    Ok(())
+}
+
+fn literary_file(file: PathBuf, words: Option<PathBuf>, seed: u64) -> Result<()> {
+    let src = read_source(file);
+    let words = words.map(read_source);
+    let words: Vec<&str> = match &words {
+        Some(words) => words.split_whitespace().collect(),
+        None => literary::WORDS.to_vec(),
+    };
+
+    let rewritten = literary(&src, &words, seed);
+
+    let (_, found) = equiv::check(
+        &Aheui::new(src),
+        &Aheui::new(rewritten.clone()),
+        Bounds::default(),
+    );
+    if let Some(found) = found {
+        return Err(eyre!(
+            "rewritten program differs on input {:?}",
+            found.input
+        ));
+    }
+
+    print!("{}", rewritten);
+
+    Ok(())
+}
+
+fn transform_file(file: PathBuf, t: Transform, verify: bool) -> Result<()> {
+    let aheui = Aheui::new(read_source(file));
+    let transformed = transform(&aheui, t).ok_or_else(|| {
+        eyre!("program wraps around the grid edge in a way the entry prelude would break")
+    })?;
+
+    if verify {
+        let (_, found) = equiv::check(&aheui, &Aheui::new(transformed.clone()), Bounds::default());
+        if let Some(found) = found {
+            return Err(eyre!(
+                "transformed program differs on input {:?}",
+                found.input
+            ));
+        }
+    }
+
+    print!("{}", transformed);
+
+    Ok(())
+}
+
+fn aheuilize_file(
+    file: PathBuf,
+    layout: Layout,
+    verify: bool,
+    source_map: Option<PathBuf>,
+) -> Result<()> {
+    let text = read_source(file);
+    let (program, map) = aheuilize_with_map(&text, layout);
+    write_source_map(source_map, &map)?;
+
+    if verify {
+        let outcome = equiv::run(&Aheui::new(program.clone()), "", Bounds::default().steps);
+        if outcome.output != text || outcome.exit != Some(ExitCode::Success(0)) {
+            return Err(eyre!(
+                "generated program printed {:?} and exited with {:?}",
+                outcome.output,
+                outcome.exit
+            ));
+        }
+    }
+
+    print!("{}", program);
+
+    Ok(())
+}
+
+fn constant(numbers: Vec<i32>, cost: Cost, verify: bool) -> Result<()> {
+    let mut synth = Synthesizer::new(cost);
+
+    for n in numbers {
+        let ops = synth.synthesize(n);
+        let code = constant::render(&ops, 'ㅏ');
+
+        if verify {
+            let outcome = equiv::run(&Aheui::new(format!("{}망하", code)), "", ops.len() + 2);
+            if outcome.output != n.to_string() {
+                return Err(eyre!(
+                    "{} printed {:?} instead of {}",
+                    code,
+                    outcome.output,
+                    n
+                ));
+            }
+        }
+
+        println!("{}\t{}\t{}", n, ops.len(), code);
+    }
+
+    Ok(())
+}
+
+fn asm(
+    file: PathBuf,
+    output: Option<PathBuf>,
+    source_map: Option<PathBuf>,
+    module: bool,
+) -> Result<()> {
+    let src = read_source(file);
+    let (grid, map) = match module {
+        true => asm::assemble_module(&src)?,
+        false => assemble_with_map(&src)?,
+    };
+    write_source_map(source_map, &map)?;
+
+    match output {
+        Some(path) => fs::write(path, grid)?,
+        None => print!("{}", grid),
+    }
+
+    Ok(())
+}
+
+fn svg_file(
+    file: PathBuf,
+    trace: bool,
+    input: Option<PathBuf>,
+    steps: usize,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let aheui = Aheui::new(read_source(file));
+    let path = if trace {
+        let input = input
+            .map(fs::read_to_string)
+            .transpose()?
+            .unwrap_or_default();
+        Some(svg::trace(&aheui, &input, steps))
+    } else {
+        None
+    };
+    let image = svg::render(&aheui, path.as_deref());
+
+    match output {
+        Some(path) => fs::write(path, image)?,
+        None => print!("{}", image),
+    }
+
+    Ok(())
+}
+
+fn image(
+    file: PathBuf,
+    output: PathBuf,
+    mode: Mode,
+    scale: usize,
+    input: Option<PathBuf>,
+    steps: usize,
+) -> Result<()> {
+    if scale == 0 {
+        return Err(eyre!("scale must be at least 1"));
+    }
+
+    let aheui = Aheui::new(read_source(file));
+    // 0픽셀 PNG는 대부분의 뷰어가 열지 못한다
+    if aheui.src_mat.0 == 0 || aheui.src_mat.1 == 0 {
+        return Err(eyre!("nothing to draw in an empty grid"));
+    }
+    let counts = match mode {
+        Mode::Opcode => None,
+        Mode::Heat => {
+            let input = input
+                .map(fs::read_to_string)
+                .transpose()?
+                .unwrap_or_default();
+            Some(raster::heat(&aheui, &input, steps))
+        }
+    };
+    let image = raster::render(&aheui, counts.as_deref(), scale);
+
+    let bytes = match output.extension().and_then(|e| e.to_str()) {
+        Some("ppm") => image.to_ppm(),
+        _ => image.to_png(),
+    };
+    fs::write(output, bytes)?;
+
+    Ok(())
+}
+
 /// Debuggable Aheui Interpreter
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Print debug
     #[arg(long, short)]
     debug: bool,
 
+    /// Halt when the machine state repeats, checking every INTERVAL steps
+    #[arg(long, value_name = "INTERVAL", num_args = 0..=1, require_equals = true, default_missing_value = "1")]
+    detect_loops: Option<usize>,
+
+    /// Source map from a generator; defaults to FILE.map when it exists
+    #[arg(long, value_name = "MAP")]
+    source_map: Option<PathBuf>,
+
+    /// Stop before a matching step, e.g. `op ㅎ`, `3,4 if top == 0` or `any if len 앗 > 100`;
+    /// with `log MESSAGE` print MESSAGE to stderr instead of stopping
+    #[arg(long = "break", value_name = "SPEC")]
+    breakpoints: Vec<Breakpoint>,
+
     /// Aheui source code
-    file: std::path::PathBuf,
+    #[arg(required = true)]
+    file: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Report stack checks that always pass or always reflect
+    Lint {
+        /// Aheui source code
+        file: PathBuf,
+
+        /// Also report checks that always pass or depend on the path
+        #[arg(long, short)]
+        all: bool,
+    },
+    /// Search for an input file that makes execution reach a cell, failing when there is none
+    Reach {
+        /// Aheui source code
+        file: PathBuf,
+
+        /// Target column
+        x: i32,
+
+        /// Target row
+        y: i32,
+
+        /// Maximum steps along a single path
+        #[arg(long, default_value_t = 10_000)]
+        steps: usize,
+
+        /// Write the input here instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Blank out unreachable cells and drop rows and columns never entered
+    Minify {
+        /// Aheui source code
+        file: PathBuf,
+
+        /// Non-Hangul character for unreachable cells
+        #[arg(long, default_value_t = 'ㅇ')]
+        filler: char,
+
+        /// Check the result against the original with `equiv`
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Rewrite every syllable to a canonical one with the same behavior
+    Normalize {
+        /// Aheui source code
+        file: PathBuf,
+
+        /// Fail instead of printing when the source would change
+        #[arg(long)]
+        check: bool,
+    },
+    /// Rewrite syllables into equivalent ones that read like prose
+    Literary {
+        /// Aheui source code
+        file: PathBuf,
+
+        /// Whitespace separated word list to draw from
+        #[arg(long, short)]
+        words: Option<PathBuf>,
+
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Rotate, transpose or mirror a program without changing its behavior
+    Transform {
+        /// rotate90, rotate180, rotate270, transpose, mirror-h or mirror-v
+        transform: Transform,
+
+        /// Aheui source code
+        file: PathBuf,
+
+        /// Check the result against the original with `equiv`
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Generate a program that prints the contents of a text file
+    Aheuilize {
+        /// Text to print
+        file: PathBuf,
+
+        /// Maximum cells per row, or per column with `columns`; 0 never folds
+        #[arg(long, short, default_value_t = 40)]
+        width: usize,
+
+        /// Start a new row after every newline in the text
+        #[arg(long)]
+        wrap_lines: bool,
+
+        /// snake, columns or literary
+        #[arg(long, short, default_value = "snake")]
+        style: Style,
+
+        /// Run the result and check that it prints the text
+        #[arg(long)]
+        verify: bool,
+
+        /// Write a map from each cell to the character it prints
+        #[arg(long, value_name = "MAP")]
+        source_map: Option<PathBuf>,
+    },
+    /// Lay out linear assembly as an Aheui grid
+    Asm {
+        /// Assembly source, one instruction per line
+        file: PathBuf,
+
+        /// Write the grid here instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Write a map from each cell to the line that made it
+        #[arg(long, value_name = "MAP")]
+        source_map: Option<PathBuf>,
+
+        /// Leave through the bottom edge instead of halting, for `link`
+        #[arg(long)]
+        module: bool,
+    },
+    /// Compile Brainfuck to an Aheui grid
+    Bf {
+        /// Brainfuck source
+        file: PathBuf,
+
+        /// Write the grid here instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Stop at the intermediate assembly
+        #[arg(long)]
+        asm: bool,
+
+        /// Run the grid and a reference interpreter and compare their output
+        #[arg(long)]
+        verify: bool,
+
+        /// Input for `--verify`
+        #[arg(long, short, requires = "verify")]
+        input: Option<PathBuf>,
+
+        /// Brainfuck step budget for `--verify`
+        #[arg(long, default_value_t = 1_000_000)]
+        steps: usize,
+
+        /// Write a map from each cell to the Brainfuck command that made it
+        #[arg(long, value_name = "MAP", conflicts_with = "asm")]
+        source_map: Option<PathBuf>,
+    },
+    /// Compile a small structured language with variables, loops and functions
+    Compile {
+        /// Program source
+        file: PathBuf,
+
+        /// Write the grid here instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Stop at the intermediate assembly
+        #[arg(long)]
+        asm: bool,
+
+        /// Run the grid and a reference interpreter and compare their output
+        #[arg(long)]
+        verify: bool,
+
+        /// Input for `--verify`
+        #[arg(long, short, requires = "verify")]
+        input: Option<PathBuf>,
+
+        /// Statement and expression budget for `--verify`
+        #[arg(long, default_value_t = 1_000_000)]
+        steps: usize,
+
+        /// Write a map from each cell to the statement that made it
+        #[arg(long, value_name = "MAP", conflicts_with = "asm")]
+        source_map: Option<PathBuf>,
+    },
+    /// Stack module grids so each one runs after the previous leaves its bottom edge
+    Link {
+        /// Module grids in execution order; `std:NAME` is a standard library routine
+        #[arg(required = true)]
+        modules: Vec<PathBuf>,
+
+        /// Write the linked grid here instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// A storage that several modules may use, e.g. to pass values
+        #[arg(long, short)]
+        shared: Vec<String>,
+    },
+    /// List the standard library, or write one routine as a link module
+    Stdlib {
+        /// Routine to write; without it every routine is checked and listed
+        name: Option<String>,
+
+        /// Write the routine here instead of stdout
+        #[arg(long, short, requires = "name")]
+        output: Option<PathBuf>,
+
+        /// Write the assembly source instead of the grid
+        #[arg(long, requires = "name")]
+        asm: bool,
+    },
+    /// List reachable cells as labelled runs of decoded instructions
+    Disasm {
+        /// Aheui source code
+        file: PathBuf,
+    },
+    /// Export the control-flow graph of straight-line runs
+    Cfg {
+        /// Aheui source code
+        file: PathBuf,
+
+        /// dot or text
+        #[arg(long, short, default_value = "dot")]
+        format: Format,
+    },
+    /// Draw the grid as SVG, colored by opcode family with direction arrows
+    Svg {
+        /// Aheui source code
+        file: PathBuf,
+
+        /// Overlay the path of an actual run
+        #[arg(long)]
+        trace: bool,
+
+        /// Input for the traced run
+        #[arg(long, short, requires = "trace")]
+        input: Option<PathBuf>,
+
+        /// Step budget for the traced run
+        #[arg(long, default_value_t = 10_000, requires = "trace")]
+        steps: usize,
+
+        /// Write the image here instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Render the grid as a PNG or PPM image, one block per cell
+    Image {
+        /// Aheui source code
+        file: PathBuf,
+
+        /// Image file; PPM if it ends in `.ppm`, PNG otherwise
+        #[arg(long, short)]
+        output: PathBuf,
+
+        /// opcode, or heat to color by execution count
+        #[arg(long, short, default_value = "opcode")]
+        mode: Mode,
+
+        /// Pixels per cell side
+        #[arg(long, default_value_t = 8)]
+        scale: usize,
+
+        /// Input for the heat run
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        /// Step budget for the heat run
+        #[arg(long, default_value_t = 1_000_000)]
+        steps: usize,
+    },
+    /// Rebuild loops and branches as pseudocode over named storages
+    Decompile {
+        /// Aheui source code
+        file: PathBuf,
+    },
+    /// Find a short syllable sequence that pushes each integer
+    Constant {
+        #[arg(required = true, allow_negative_numbers = true)]
+        numbers: Vec<i32>,
+
+        /// Weight of each `ㅂ`
+        #[arg(long, default_value_t = 1)]
+        push_cost: u32,
+
+        /// Weight of each `ㄷ`, `ㄸ`, `ㅌ` and `ㄴ`
+        #[arg(long, default_value_t = 1)]
+        arith_cost: u32,
+
+        /// Weight of each `ㅃ`
+        #[arg(long, default_value_t = 1)]
+        dup_cost: u32,
+
+        /// Weight of each `ㅍ`
+        #[arg(long, default_value_t = 1)]
+        swap_cost: u32,
+
+        /// Run each sequence and check the pushed value
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Compare two programs on enumerated and random inputs
+    Equiv {
+        left: PathBuf,
+
+        right: PathBuf,
+
+        /// Enumerate every input with up to this many lines
+        #[arg(long, default_value_t = 2)]
+        lines: usize,
+
+        /// Step budget for each run
+        #[arg(long, default_value_t = 100_000)]
+        steps: usize,
+
+        /// Random inputs to try after enumeration
+        #[arg(long, default_value_t = 100)]
+        random: usize,
+
+        #[arg(long, default_value_t = 0x5eed)]
+        seed: u64,
+    },
+    /// Search for a smaller grid that still passes the given tests.
+    ///
+    /// Tries deleting rows, columns and cells, shortening the cells that
+    /// build constants and merging paths that do the same work.
+    Golf {
+        /// Aheui source code
+        file: PathBuf,
+
+        /// Expected output, with input from the same path ending in `.in`.
+        /// Defaults to FILE ending in `.out`
+        #[arg(long = "case", value_name = "OUT")]
+        cases: Vec<PathBuf>,
+
+        /// Step budget for the original program on each case
+        #[arg(long, default_value_t = 1_000_000)]
+        steps: usize,
+
+        /// Write the smallest grid here instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Evolve a small grid that prints the target (experimental)
+    Evolve {
+        /// Output to aim for
+        #[arg(long)]
+        target: String,
+
+        /// Input for every run
+        #[arg(long, short)]
+        input: Option<PathBuf>,
+
+        /// Candidates kept each generation
+        #[arg(long, default_value_t = 200)]
+        population: usize,
+
+        #[arg(long, default_value_t = 1000)]
+        generations: usize,
+
+        /// Step budget for each run
+        #[arg(long, default_value_t = 1000)]
+        steps: usize,
+
+        /// Widest grid to try
+        #[arg(long, default_value_t = 8)]
+        width: usize,
+
+        /// Tallest grid to try
+        #[arg(long, default_value_t = 8)]
+        height: usize,
+
+        #[arg(long, default_value_t = 0x5eed)]
+        seed: u64,
+
+        /// Write the best grid here instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_rejects_an_empty_grid() {
+        let dir = std::env::temp_dir().join(format!("oxidaheuize-image-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("empty.aheui");
+        let output = dir.join("empty.png");
+        for src in ["", "\n\n"] {
+            fs::write(&file, src).unwrap();
+            let result = image(file.clone(), output.clone(), Mode::Opcode, 1, None, 10);
+            assert!(result.is_err(), "{src:?}");
+            assert!(!output.exists());
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
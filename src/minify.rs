@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+
+use crate::aheui::{Aheui, advance};
+use crate::analysis::{DepthAnalysis, State};
+
+/// 실행되지 않는 셀을 `filler`로 바꾸고, 들어가지 않는 행과 열을 지운다.
+///
+/// 행이나 열을 지우면 감싸는 크기(`src_mat`)와 두 칸 이동이 바뀌므로, 도달 가능한
+/// 모든 이동이 새 격자에서도 같은 셀로 이어질 때만 지운다.
+/// `filler`는 한글 음절이 아니어야 한다.
+pub fn minify(aheui: &Aheui, filler: char) -> String {
+    let (width, height) = aheui.src_mat;
+    if width == 0 || height == 0 {
+        return String::new();
+    }
+
+    let analysis = DepthAnalysis::run(aheui);
+    let edges = analysis.edges(aheui);
+    let entered: HashSet<(i32, i32)> = analysis.states.keys().map(|s| s.cursor).collect();
+
+    // 모두 남긴 상태에서 시작해 들어가지 않는 행과 열을 하나씩 지워 본다
+    let mut keep_cols = vec![true; width];
+    let mut keep_rows = vec![true; height];
+    for x in (0..width).rev() {
+        if !entered.iter().any(|c| c.0 == x as i32) {
+            keep_cols[x] = false;
+            if !preserves(&edges, &keep_cols, &keep_rows) {
+                keep_cols[x] = true;
+            }
+        }
+    }
+    for y in (0..height).rev() {
+        if !entered.iter().any(|c| c.1 == y as i32) {
+            keep_rows[y] = false;
+            if !preserves(&edges, &keep_cols, &keep_rows) {
+                keep_rows[y] = true;
+            }
+        }
+    }
+
+    let mut lines: Vec<String> = aheui
+        .src_map
+        .iter()
+        .enumerate()
+        .filter(|(y, _)| keep_rows[*y])
+        .map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(x, _)| keep_cols[*x])
+                .map(|(x, cell)| {
+                    // 한글이 아닌 셀은 지나가기만 하므로 장식
+                    if cell.0 != ' ' && entered.contains(&(x as i32, y as i32)) {
+                        cell.3
+                    } else {
+                        filler
+                    }
+                })
+                .collect::<String>()
+                .trim_end_matches(filler)
+                .to_string()
+        })
+        .collect();
+
+    // 가장 긴 줄이 너비를 정하므로 너비가 줄었으면 한 줄을 채운다
+    let new_width = keep_cols.iter().filter(|&&k| k).count();
+    if lines.iter().all(|l| l.chars().count() < new_width) {
+        let pad = new_width - lines[0].chars().count();
+        lines[0].extend(std::iter::repeat_n(filler, pad));
+    }
+
+    lines.iter().map(|l| format!("{}\n", l)).collect()
+}
+
+/// 남긴 행과 열로 좌표를 옮겨도 모든 이동이 같은 셀로 가는지
+fn preserves(edges: &[(State, State)], keep_cols: &[bool], keep_rows: &[bool]) -> bool {
+    let remap = |keep: &[bool]| -> Vec<i32> {
+        keep.iter()
+            .scan(0, |next, &k| {
+                let idx = *next;
+                *next += k as i32;
+                Some(idx)
+            })
+            .collect()
+    };
+    let xs = remap(keep_cols);
+    let ys = remap(keep_rows);
+    let mat = (
+        keep_cols.iter().filter(|&&k| k).count(),
+        keep_rows.iter().filter(|&&k| k).count(),
+    );
+    let map = |(x, y): (i32, i32)| (xs[x as usize], ys[y as usize]);
+
+    edges
+        .iter()
+        .all(|(from, to)| advance(map(from.cursor), to.dir, mat) == map(to.cursor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equiv::{SAMPLES, assert_equivalent};
+
+    #[test]
+    fn keeps_the_behavior() {
+        for src in SAMPLES {
+            assert_equivalent(src, &minify(&Aheui::new(src.to_string()), 'ㅇ'));
+        }
+    }
+
+    #[test]
+    fn drops_what_never_runs() {
+        // 둘째 행은 실행되지 않는다
+        let src = "바망희\n반받다\n";
+        assert_eq!(minify(&Aheui::new(src.to_string()), 'ㅇ'), "바망희\n");
+        // 지나가는 행은 비어도 남긴다
+        let src = "우\nㅇ\n희\n";
+        assert_eq!(minify(&Aheui::new(src.to_string()), 'ㅇ'), "우\n\n희\n");
+    }
+}
@@ -0,0 +1,91 @@
+use crate::hangul::{KChar, assemble_jamo, count_lines_in_char, disassemble_jamo, jamo::*};
+
+/// 인터프리터가 같게 실행하는 음절 중 대표 음절.
+/// 한글 음절이 아니면 그대로 둔다
+pub fn canonical(chr: KChar) -> char {
+    if chr.0 == ' ' {
+        return chr.3;
+    }
+
+    let (start, middle, end) = canonical_jamo(chr);
+    assemble_jamo(start, middle, end)
+}
+
+/// 닿소리, 홀소리, 받침 각각의 대표
+pub fn canonical_jamo(chr: KChar) -> (char, char, (char, char)) {
+    let start = match chr.0 {
+        // 지나감
+        'ㄱ' | 'ㄲ' => 'ㅇ',
+        // `Aheui::next`에 없으므로 반사
+        'ㅉ' => 'ㅋ',
+        c => c,
+    };
+
+    // 끝나면 방향을 보지 않음
+    if start == 'ㅎ' {
+        return ('ㅎ', 'ㅏ', (' ', ' '));
+    }
+
+    let middle = if is_direction(chr.1) { chr.1 } else { 'ㅐ' };
+
+    let end = match start {
+        'ㅁ' => match chr.2 {
+            ('ㅇ', ' ') | ('ㅎ', ' ') => chr.2,
+            _ => (' ', ' '),
+        },
+        'ㅂ' => match chr.2 {
+            ('ㅇ', ' ') | ('ㅎ', ' ') => chr.2,
+            // 같은 획수를 가진 받침 중 하나
+            end => canonical_count(count_lines_in_char(end)),
+        },
+        'ㅅ' | 'ㅆ' => chr.2,
+        _ => (' ', ' '),
+    };
+
+    (start, middle, end)
+}
+
+/// `Aheui::next`에서 방향을 바꾸는 홀소리
+pub fn is_direction(vowel: char) -> bool {
+    matches!(
+        vowel,
+        'ㅏ' | 'ㅓ' | 'ㅗ' | 'ㅜ' | 'ㅑ' | 'ㅕ' | 'ㅛ' | 'ㅠ' | 'ㅣ' | 'ㅡ' | 'ㅢ'
+    )
+}
+
+/// 홑받침을 겹받침보다 먼저 고른다
+pub fn canonical_count(count: i32) -> (char, char) {
+    let mut ends: Vec<(char, char)> = _END
+        .iter()
+        .copied()
+        .filter(|&end| {
+            end != ('ㅇ', ' ') && end != ('ㅎ', ' ') && count_lines_in_char(end) == count
+        })
+        .collect();
+    ends.sort_by_key(|end| end.1 != ' ');
+
+    ends.first().copied().unwrap_or((' ', ' '))
+}
+
+/// 줄 구성과 한글이 아닌 글자는 두고 음절만 대표로 바꾼다
+pub fn normalize(src: &str) -> String {
+    src.chars()
+        .map(|c| canonical(disassemble_jamo(c)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equiv::{SAMPLES, assert_equivalent};
+
+    #[test]
+    fn keeps_the_behavior() {
+        for src in SAMPLES {
+            let normalized = normalize(src);
+            assert_equivalent(src, &normalized);
+            assert_eq!(normalize(&normalized), normalized);
+            assert_eq!(normalized.lines().count(), src.lines().count());
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use std::str::FromStr;
+
+use crate::aheui::Aheui;
+use crate::svg::{FAMILIES, family, trace};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// `svg`와 같은 명령 묶음별 색
+    Opcode,
+    /// 실행된 횟수. 로그 눈금
+    Heat,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "opcode" => Ok(Self::Opcode),
+            "heat" => Ok(Self::Heat),
+            _ => Err(format!("unknown mode {:?}, expected opcode or heat", s)),
+        }
+    }
+}
+
+/// RGB 픽셀을 행 순서로
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+fn hex(color: &str) -> [u8; 3] {
+    let channel = |i: usize| u8::from_str_radix(&color[i..i + 2], 16).unwrap_or(0);
+    [channel(1), channel(3), channel(5)]
+}
+
+/// 0..=1을 옅은 노랑, 주황, 진한 빨강으로
+fn heat_color(t: f64) -> [u8; 3] {
+    const STOPS: [[f64; 3]; 3] = [[255., 255., 178.], [253., 141., 60.], [189., 0., 38.]];
+
+    let t = t.clamp(0., 1.) * 2.;
+    let (lo, hi) = if t < 1. {
+        (STOPS[0], STOPS[1])
+    } else {
+        (STOPS[1], STOPS[2])
+    };
+    let f = t - t.floor().min(1.);
+    let mix = |i: usize| (lo[i] + (hi[i] - lo[i]) * f).round() as u8;
+    [mix(0), mix(1), mix(2)]
+}
+
+/// 셀마다 지난 횟수. 빈 격자는 지나는 셀이 없다
+pub fn heat(aheui: &Aheui, input: &str, steps: usize) -> Vec<Vec<usize>> {
+    let mut counts = vec![vec![0; aheui.src_mat.0]; aheui.src_mat.1];
+    for ((x, y), _) in trace(aheui, input, steps) {
+        counts[y as usize][x as usize] += 1;
+    }
+    counts
+}
+
+/// 셀 하나를 `scale` x `scale` 칸으로 칠한다. `counts`가 있으면 실행 횟수로
+pub fn render(aheui: &Aheui, counts: Option<&[Vec<usize>]>, scale: usize) -> Image {
+    let (w, h) = aheui.src_mat;
+    let max = counts
+        .and_then(|c| c.iter().flatten().max().copied())
+        .unwrap_or(0);
+    // 한 번도 지나지 않은 셀
+    let unvisited = hex(FAMILIES[3].1);
+
+    let mut image = Image {
+        width: w * scale,
+        height: h * scale,
+        pixels: vec![[255; 3]; w * h * scale * scale],
+    };
+    for (y, row) in aheui.src_map.iter().enumerate() {
+        for (x, &chr) in row.iter().enumerate() {
+            let color = match counts {
+                None => hex(FAMILIES[family(chr)].1),
+                Some(counts) => match counts[y][x] {
+                    0 if chr.0 == ' ' => [255; 3],
+                    0 => unvisited,
+                    n => heat_color((n as f64).ln_1p() / (max as f64).ln_1p()),
+                },
+            };
+            for py in y * scale..(y + 1) * scale {
+                let start = py * image.width + x * scale;
+                image.pixels[start..start + scale].fill(color);
+            }
+        }
+    }
+
+    image
+}
+
+impl Image {
+    /// 바이너리 PPM (P6)
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut out = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        out.extend(self.pixels.iter().flatten());
+        out
+    }
+
+    /// 8비트 RGB PNG. 같은 행이 이어지면 Up, 아니면 Sub 필터를 쓰고
+    /// 같은 바이트가 이어지는 곳만 찾는 deflate로 줄인다
+    pub fn to_png(&self) -> Vec<u8> {
+        let stride = self.width * 3;
+        let mut raw = Vec::with_capacity((stride + 1) * self.height);
+        let mut prev: &[[u8; 3]] = &[];
+        for y in 0..self.height {
+            let row = &self.pixels[y * self.width..(y + 1) * self.width];
+            if row == prev {
+                raw.push(2);
+                raw.extend(std::iter::repeat_n(0, stride));
+            } else {
+                raw.push(1);
+                let bytes: Vec<u8> = row.iter().flatten().copied().collect();
+                for i in 0..stride {
+                    let left = if i >= 3 { bytes[i - 3] } else { 0 };
+                    raw.push(bytes[i].wrapping_sub(left));
+                }
+            }
+            prev = row;
+        }
+
+        let mut ihdr = vec![];
+        ihdr.extend((self.width as u32).to_be_bytes());
+        ihdr.extend((self.height as u32).to_be_bytes());
+        // 비트 깊이 8, RGB, deflate, 필터 0, 인터레이스 없음
+        ihdr.extend([8, 2, 0, 0, 0]);
+
+        let mut zlib = vec![0x78, 0x01];
+        zlib.extend(deflate(&raw));
+        zlib.extend(adler32(&raw).to_be_bytes());
+
+        let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut out, b"IHDR", &ihdr);
+        chunk(&mut out, b"IDAT", &zlib);
+        chunk(&mut out, b"IEND", &[]);
+        out
+    }
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend((data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend(kind);
+    out.extend(data);
+    let crc = crc32(&out[start..]);
+    out.extend(crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// 바이트의 낮은 비트부터 채운다
+struct Bits {
+    out: Vec<u8>,
+    acc: u32,
+    len: u32,
+}
+
+impl Bits {
+    fn push(&mut self, value: u32, len: u32) {
+        self.acc |= value << self.len;
+        self.len += len;
+        while self.len >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    /// 허프만 부호는 높은 비트부터
+    fn push_code(&mut self, code: u32, len: u32) {
+        let reversed = code.reverse_bits() >> (32 - len);
+        self.push(reversed, len);
+    }
+}
+
+/// 길이 부호 257..=285의 (시작 길이, 추가 비트)
+const LENGTHS: [(u32, u32); 29] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 1),
+    (13, 1),
+    (15, 1),
+    (17, 1),
+    (19, 2),
+    (23, 2),
+    (27, 2),
+    (31, 2),
+    (35, 3),
+    (43, 3),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 4),
+    (115, 4),
+    (131, 5),
+    (163, 5),
+    (195, 5),
+    (227, 5),
+    (258, 0),
+];
+
+/// 고정 허프만 부호
+fn fixed_code(bits: &mut Bits, symbol: u32) {
+    match symbol {
+        0..=143 => bits.push_code(0x30 + symbol, 8),
+        144..=255 => bits.push_code(0x190 + symbol - 144, 9),
+        256..=279 => bits.push_code(symbol - 256, 7),
+        _ => bits.push_code(0xc0 + symbol - 280, 8),
+    }
+}
+
+/// 고정 허프만 블록 하나. 바로 앞 바이트가 이어지는 곳만 거리 1로 되짚는다
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut bits = Bits {
+        out: vec![],
+        acc: 0,
+        len: 0,
+    };
+    // BFINAL, 고정 허프만
+    bits.push(1, 1);
+    bits.push(1, 2);
+
+    let mut i = 0;
+    while i < data.len() {
+        let run = if i == 0 {
+            0
+        } else {
+            data[i..]
+                .iter()
+                .take(258)
+                .take_while(|&&b| b == data[i - 1])
+                .count()
+        };
+        if run < 3 {
+            fixed_code(&mut bits, data[i] as u32);
+            i += 1;
+            continue;
+        }
+
+        let code = LENGTHS
+            .iter()
+            .rposition(|&(base, _)| base as usize <= run)
+            .unwrap();
+        let (base, extra) = LENGTHS[code];
+        fixed_code(&mut bits, 257 + code as u32);
+        bits.push(run as u32 - base, extra);
+        // 거리 1은 부호 0, 추가 비트 없음
+        bits.push_code(0, 5);
+        i += run;
+    }
+    fixed_code(&mut bits, 256);
+    bits.push(0, 7);
+
+    bits.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 비트를 낮은 쪽부터 읽는다
+    struct Reader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl Reader<'_> {
+        fn bits(&mut self, len: u32) -> u32 {
+            (0..len).fold(0, |acc, i| {
+                let bit = (self.data[self.pos / 8] >> (self.pos % 8)) & 1;
+                self.pos += 1;
+                acc | (bit as u32) << i
+            })
+        }
+
+        /// 허프만 부호는 높은 비트부터
+        fn code(&mut self, len: u32) -> u32 {
+            (0..len).fold(0, |acc, _| acc << 1 | self.bits(1))
+        }
+
+        fn symbol(&mut self) -> u32 {
+            let code = self.code(7);
+            if code <= 0x17 {
+                return 256 + code;
+            }
+            let code = code << 1 | self.code(1);
+            match code {
+                0x30..=0xbf => code - 0x30,
+                0xc0..=0xc7 => 280 + code - 0xc0,
+                _ => 144 + (code << 1 | self.code(1)) - 0x190,
+            }
+        }
+    }
+
+    /// 고정 허프만 블록 하나만 푼다
+    fn inflate(data: &[u8]) -> Vec<u8> {
+        let mut reader = Reader { data, pos: 0 };
+        assert_eq!(reader.bits(1), 1, "BFINAL");
+        assert_eq!(reader.bits(2), 1, "fixed Huffman");
+
+        let mut out: Vec<u8> = vec![];
+        loop {
+            match reader.symbol() {
+                byte @ 0..=255 => out.push(byte as u8),
+                256 => return out,
+                symbol => {
+                    let (base, extra) = LENGTHS[symbol as usize - 257];
+                    let len = base + reader.bits(extra);
+                    let distance = reader.code(5);
+                    assert!(distance < 4, "distance code {}", distance);
+                    for _ in 0..len {
+                        out.push(out[out.len() - distance as usize - 1]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn checksums_match_known_vectors() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn deflate_round_trips() {
+        let mut long = vec![7; 600];
+        long.extend([200, 200, 200, 1, 1, 2, 2, 2, 2, 143, 144, 255, 0]);
+        for data in [vec![], vec![0], b"aaa".to_vec(), b"abcabc".to_vec(), long] {
+            assert_eq!(inflate(&deflate(&data)), data);
+        }
+        // 첫 바이트 뒤로 259바이트가 이어져도 한 번에 258까지만 되짚는다
+        let data = vec![9; 260];
+        assert_eq!(inflate(&deflate(&data)), data);
+        assert!(deflate(&data).len() < 10);
+    }
+
+    #[test]
+    fn writes_chunks_with_length_and_crc() {
+        let mut out = vec![];
+        chunk(&mut out, b"IEND", &[]);
+        assert_eq!(out, b"\0\0\0\0IEND\xae\x42\x60\x82");
+    }
+
+    #[test]
+    fn png_decodes_to_the_pixels() {
+        let aheui = Aheui::new("방망희\n아ㅇ\n".to_string());
+        let image = render(&aheui, None, 2);
+        let png = image.to_png();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+        // 청크마다 CRC를 확인하며 IDAT를 모은다
+        let mut pos = 8;
+        let mut idat = vec![];
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let body = &png[pos + 4..pos + 8 + len];
+            let crc = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+            assert_eq!(crc32(body), crc);
+            if &body[..4] == b"IDAT" {
+                idat.extend(&body[4..]);
+            }
+            pos += 12 + len;
+        }
+        assert_eq!(pos, png.len());
+
+        let raw = inflate(&idat[2..idat.len() - 4]);
+        assert_eq!(
+            u32::from_be_bytes(idat[idat.len() - 4..].try_into().unwrap()),
+            adler32(&raw)
+        );
+
+        // Sub와 Up 필터를 되돌린다
+        let stride = image.width * 3;
+        let mut pixels: Vec<u8> = vec![];
+        for (y, row) in raw.chunks(stride + 1).enumerate() {
+            for (i, &byte) in row[1..].iter().enumerate() {
+                let base = match row[0] {
+                    1 if i >= 3 => pixels[y * stride + i - 3],
+                    2 => pixels[(y - 1) * stride + i],
+                    _ => 0,
+                };
+                pixels.push(byte.wrapping_add(base));
+            }
+        }
+        assert_eq!(pixels, image.pixels.concat());
+    }
+
+    #[test]
+    fn heats_nothing_on_an_empty_grid() {
+        assert!(heat(&Aheui::new(String::new()), "", 10).is_empty());
+        assert_eq!(
+            heat(&Aheui::new("\n\n".to_string()), "", 10),
+            vec![vec![]; 2]
+        );
+    }
+
+    #[test]
+    fn counts_each_visit() {
+        // 끝내는 셀까지 한 번씩 지난다
+        let counts = heat(&Aheui::new("반망희\n".to_string()), "", 100);
+        assert_eq!(counts, vec![vec![1, 1, 1]]);
+    }
+}
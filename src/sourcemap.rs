@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// 입력의 (줄, 칸). 둘 다 1부터
+pub type Pos = (usize, usize);
+
+/// 만들어 낸 격자의 셀마다 그 셀을 만든 입력의 자리
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    cells: HashMap<(i32, i32), Pos>,
+}
+
+impl SourceMap {
+    pub fn insert(&mut self, cell: (i32, i32), pos: Pos) {
+        self.cells.insert(cell, pos);
+    }
+
+    pub fn get(&self, cell: (i32, i32)) -> Option<Pos> {
+        self.cells.get(&cell).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// 중간 입력의 줄(1부터)을 처음 입력의 자리로 바꾼다. `origins[줄 - 1]`
+    pub fn compose(&self, origins: &[Pos]) -> SourceMap {
+        SourceMap {
+            cells: self
+                .cells
+                .iter()
+                .filter_map(|(&cell, &(line, _))| Some((cell, *origins.get(line.checked_sub(1)?)?)))
+                .collect(),
+        }
+    }
+
+    /// 한 줄에 `x y 줄 칸`. `#` 뒤는 주석
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut map = SourceMap::default();
+
+        for (idx, line) in text.lines().enumerate() {
+            let code = line.split('#').next().unwrap_or("").trim();
+            if code.is_empty() {
+                continue;
+            }
+            let nums: Vec<&str> = code.split_whitespace().collect();
+            let [x, y, l, c] = nums.as_slice() else {
+                return Err(format!("line {}: expected x y line column", idx + 1));
+            };
+            let err = |_| format!("line {}: invalid number", idx + 1);
+            map.insert(
+                (x.parse().map_err(err)?, y.parse().map_err(err)?),
+                (l.parse().map_err(err)?, c.parse().map_err(err)?),
+            );
+        }
+
+        Ok(map)
+    }
+}
+
+impl fmt::Display for SourceMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut cells: Vec<_> = self.cells.iter().collect();
+        cells.sort_by_key(|&(&(x, y), _)| (y, x));
+
+        writeln!(f, "# x y line column")?;
+        for (&(x, y), &(line, col)) in cells {
+            writeln!(f, "{} {} {} {}", x, y, line, col)?;
+        }
+        Ok(())
+    }
+}
+
+/// `grid`를 실행해 처음 출력한 셀
+#[cfg(test)]
+pub(crate) fn first_print(grid: &str, input: &str) -> (i32, i32) {
+    let mut aheui = crate::aheui::Aheui::new(grid.to_string());
+    aheui.debug(true);
+    aheui.set_input(input.to_string());
+    while !aheui.ended {
+        let cell = aheui.cursor;
+        aheui.next();
+        if !aheui.result.is_empty() {
+            return cell;
+        }
+    }
+    panic!("prints nothing");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_what_it_prints() {
+        let mut map = SourceMap::default();
+        map.insert((3, 0), (1, 4));
+        map.insert((0, 2), (12, 1));
+        map.insert((1, 0), (1, 1));
+
+        let text = map.to_string();
+        assert_eq!(text, "# x y line column\n1 0 1 1\n3 0 1 4\n0 2 12 1\n");
+        assert_eq!(SourceMap::parse(&text), Ok(map));
+    }
+
+    #[test]
+    fn parses_comments_and_reports_bad_lines() {
+        let map = SourceMap::parse("\n  # cells\n2 1 3 4 # after\n").unwrap();
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get((2, 1)), Some((3, 4)));
+
+        assert_eq!(
+            SourceMap::parse("1 2 3\n"),
+            Err("line 1: expected x y line column".to_string())
+        );
+        assert_eq!(
+            SourceMap::parse("# x y line column\n1 2 3 -4\n"),
+            Err("line 2: invalid number".to_string())
+        );
+    }
+
+    #[test]
+    fn composes_through_lines_in_range() {
+        let mut map = SourceMap::default();
+        map.insert((0, 0), (1, 1));
+        map.insert((1, 0), (2, 7));
+        // 0줄과 `origins`보다 뒤의 줄은 버린다
+        map.insert((2, 0), (0, 1));
+        map.insert((3, 0), (3, 1));
+
+        let composed = map.compose(&[(10, 2), (11, 5)]);
+        assert_eq!(composed.len(), 2);
+        assert_eq!(composed.get((0, 0)), Some((10, 2)));
+        assert_eq!(composed.get((1, 0)), Some((11, 5)));
+        assert_eq!(composed.get((2, 0)), None);
+        assert_eq!(composed.get((3, 0)), None);
+        assert!(map.compose(&[]).is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::aheui::Aheui;
+use crate::asm::{self, assemble_module};
+use crate::hangul::storage_name;
+use crate::link::explore;
+use crate::sourcemap::SourceMap;
+
+/// 루틴의 계약(스택 모양이나 쓰는 저장공간)이 바뀌면 올린다
+pub const VERSION: u32 = 1;
+
+/// `link`로 이어 붙일 수 있는 검증된 조각.
+/// (0, 0)에서 `아`가 선택된 채 들어와 아래 가장자리로 나간다
+#[derive(Debug, Clone, Copy)]
+pub struct Routine {
+    pub name: &'static str,
+    pub summary: &'static str,
+    /// 실행 전과 후의 `아`. 오른쪽이 위
+    pub before: &'static str,
+    pub after: &'static str,
+    /// `아` 말고 쓰는 저장공간. 비어 있어야 하고 비운 채로 나간다
+    pub scratch: &'static [usize],
+    /// 어셈블리
+    pub src: &'static str,
+}
+
+pub const ROUTINES: &[Routine] = &[
+    Routine {
+        name: "read_ints",
+        summary: "read one line and push every integer in it, then their count",
+        before: "",
+        after: "v1 .. vn n",
+        scratch: &[1, 2, 3, 21],
+        src: include_str!("../stdlib/read_ints.asm"),
+    },
+    Routine {
+        name: "print_str",
+        summary: "print a 0-terminated string, first character on top",
+        before: "0 cn .. c1",
+        after: "",
+        scratch: &[],
+        src: include_str!("../stdlib/print_str.asm"),
+    },
+    Routine {
+        name: "isqrt",
+        summary: "floor of the square root of n >= 0",
+        before: "n",
+        after: "isqrt(n)",
+        scratch: &[4],
+        src: include_str!("../stdlib/isqrt.asm"),
+    },
+    Routine {
+        name: "gcd",
+        summary: "greatest common divisor of a, b >= 0",
+        before: "a b",
+        after: "gcd(a, b)",
+        scratch: &[7],
+        src: include_str!("../stdlib/gcd.asm"),
+    },
+    Routine {
+        name: "sort",
+        summary: "sort n values below their count, smallest next to the count",
+        before: "v1 .. vn n",
+        after: "vmax .. vmin n",
+        scratch: &[8, 9, 10, 11, 12, 13],
+        src: include_str!("../stdlib/sort.asm"),
+    },
+    Routine {
+        name: "format_int",
+        summary: "decimal digits of x as a string for print_str",
+        before: "x",
+        after: "0 dn .. d1",
+        scratch: &[17],
+        src: include_str!("../stdlib/format_int.asm"),
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Routine> {
+    ROUTINES.iter().find(|r| r.name == name)
+}
+
+impl Routine {
+    /// `link`에 넘길 격자
+    pub fn module(&self) -> Result<(String, SourceMap), asm::Error> {
+        assemble_module(self.src)
+    }
+
+    /// 격자를 따라가 보고 계약과 다르면 그 까닭
+    pub fn check(&self) -> Result<(), String> {
+        let (grid, _) = self.module().map_err(|err| err.to_string())?;
+        let (storages, exits) = explore(&Aheui::new(grid))?;
+        if !exits {
+            return Err("never reaches its bottom edge".to_string());
+        }
+
+        let declared: BTreeSet<usize> = std::iter::once(0)
+            .chain(self.scratch.iter().copied())
+            .collect();
+        if let Some(&idx) = storages.symmetric_difference(&declared).next() {
+            let verb = if storages.contains(&idx) {
+                "uses undeclared"
+            } else {
+                "never uses declared"
+            };
+            return Err(format!("{} {}", verb, storage_name(idx)));
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Routine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scratch: Vec<String> = self
+            .scratch
+            .iter()
+            .map(|&idx| storage_name(idx).to_string())
+            .collect();
+        writeln!(f, "{}: {}", self.name, self.summary)?;
+        writeln!(f, "  아: [{}] -> [{}]", self.before, self.after)?;
+        match scratch.is_empty() {
+            true => write!(f, "  scratch: none"),
+            false => write!(f, "  scratch: {}", scratch.join(" ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aheui::ExitCode;
+    use crate::equiv;
+    use crate::link::{Module, link};
+
+    // 스택의 `v1 .. vn n`을 위에서부터 찍고 줄을 바꾼다
+    const PRINT_INTS: &str = "\
+loop:   dup
+        brz done
+        push 1
+        sub
+        swap
+        print num
+        push 32
+        print char
+        jmp loop
+done:   pop
+        push 10
+        print char
+";
+    const PRINT_NUM: &str = "print num\npush 10\nprint char\n";
+    const NEWLINE: &str = "push 10\nprint char\n";
+
+    enum Part {
+        Asm(&'static str),
+        Std(&'static str),
+    }
+    use Part::{Asm, Std};
+
+    fn module(part: &Part) -> Module {
+        let (name, src) = match *part {
+            Asm(src) => ("harness", assemble_module(src).unwrap().0),
+            Std(name) => (name, find(name).unwrap().module().unwrap().0),
+        };
+        Module {
+            name: name.to_string(),
+            src,
+        }
+    }
+
+    /// 조각들을 `cases`번 이어 붙여 tests/stdlib의 격자를 다시 만들고,
+    /// 저장된 격자와 같은지, 그 입력으로 돌려 출력이 맞는지 본다
+    fn harness(name: &str, cases: usize, parts: &[Part]) {
+        let modules: Vec<Module> = (0..cases).flat_map(|_| parts.iter().map(module)).collect();
+        // 같은 루틴을 여러 번 붙이므로 모든 저장공간을 같이 쓴다
+        let shared: Vec<usize> = (0..28).collect();
+        let (grid, _) = link(&modules, &shared).unwrap();
+
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/stdlib/");
+        let read = |ext: &str| std::fs::read_to_string(format!("{dir}{name}.{ext}"));
+        assert_eq!(
+            grid,
+            read("aheui").unwrap(),
+            "tests/stdlib/{name}.aheui is stale"
+        );
+
+        let outcome = equiv::run(
+            &Aheui::new(grid),
+            &read("in").unwrap_or_default(),
+            1_000_000,
+        );
+        assert_eq!(outcome.output, read("out").unwrap(), "{name}");
+        assert!(matches!(outcome.exit, Some(ExitCode::Success(0))), "{name}");
+    }
+
+    #[test]
+    fn every_routine_keeps_its_contract() {
+        for routine in ROUTINES {
+            assert_eq!(routine.check(), Ok(()), "{}", routine.name);
+        }
+    }
+
+    #[test]
+    fn read_ints() {
+        harness("read_ints", 2, &[Std("read_ints"), Asm(PRINT_INTS)]);
+    }
+
+    #[test]
+    fn print_str() {
+        let hi = "push 0\npush 33\npush 105\npush 104\n";
+        harness("print_str", 1, &[Asm(hi), Std("print_str"), Asm(NEWLINE)]);
+    }
+
+    #[test]
+    fn isqrt() {
+        harness(
+            "isqrt",
+            6,
+            &[Asm("input num\n"), Std("isqrt"), Asm(PRINT_NUM)],
+        );
+    }
+
+    #[test]
+    fn gcd() {
+        let pair = "input num\ninput num\n";
+        harness("gcd", 4, &[Asm(pair), Std("gcd"), Asm(PRINT_NUM)]);
+    }
+
+    #[test]
+    fn sort() {
+        harness("sort", 1, &[Std("read_ints"), Std("sort"), Asm(PRINT_INTS)]);
+    }
+
+    #[test]
+    fn format_int() {
+        let parts = [
+            Asm("input num\n"),
+            Std("format_int"),
+            Std("print_str"),
+            Asm(NEWLINE),
+        ];
+        harness("format_int", 6, &parts);
+    }
+}
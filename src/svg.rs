@@ -0,0 +1,340 @@
+use crate::aheui::{Aheui, turn};
+use crate::hangul::KChar;
+
+/// 셀 한 변의 픽셀
+const CELL: i32 = 40;
+
+/// 범례 한 칸의 너비
+const LEGEND: i32 = 90;
+
+/// 명령 묶음마다의 (이름, 색)
+pub const FAMILIES: [(&str, &str); 6] = [
+    ("arithmetic", "#f9cb9c"),
+    ("storage", "#b6d7a8"),
+    ("control", "#a4c2f4"),
+    ("pass / end", "#e6e6e6"),
+    ("reflect", "#ea9999"),
+    ("non-Hangul", "#ffffff"),
+];
+
+/// `Aheui::next`의 묶음을 따른다
+pub fn family(chr: KChar) -> usize {
+    match chr.0 {
+        // ㄷ 묶음 - 셈
+        'ㄷ' | 'ㄸ' | 'ㅌ' | 'ㄴ' | 'ㄹ' => 0,
+        // ㅁ 묶음 - 저장공간
+        'ㅁ' | 'ㅂ' | 'ㅃ' | 'ㅍ' => 1,
+        // ㅅ 묶음 - 제어
+        'ㅅ' | 'ㅆ' | 'ㅈ' | 'ㅊ' => 2,
+        'ㅇ' | 'ㄱ' | 'ㄲ' | 'ㅎ' => 3,
+        ' ' => 5,
+        _ => 4,
+    }
+}
+
+fn escape(c: char) -> String {
+    match c {
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        '&' => "&amp;".to_string(),
+        c if c.is_control() => String::new(),
+        c => c.to_string(),
+    }
+}
+
+/// 홀소리가 정하는 방향을 셀 중심에서 나가는 화살표로. 반사하는 홀소리는 막대
+fn arrow(chr: KChar, cx: i32, cy: i32) -> String {
+    if chr.0 == ' ' {
+        return String::new();
+    }
+
+    let bar = |horizontal: bool| {
+        let (dx, dy) = if horizontal {
+            (CELL / 3, 0)
+        } else {
+            (0, CELL / 3)
+        };
+        format!(
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" class=\"bar\"/>",
+            cx - dx,
+            cy - dy,
+            cx + dx,
+            cy + dy
+        )
+    };
+
+    match chr.1 {
+        'ㅡ' => bar(true),
+        'ㅣ' => bar(false),
+        'ㅢ' => bar(true) + &bar(false),
+        vowel => {
+            // 아무 방향이나 넣어 보고 바뀌지 않으면 방향이 없는 홀소리
+            let dir = turn(vowel, (0, 0));
+            if dir == (0, 0) {
+                return String::new();
+            }
+            let (ux, uy) = (dir.0.signum(), dir.1.signum());
+            let (start, end) = (CELL * 3 / 10, CELL * 49 / 100);
+            let mid = if dir.0.abs() + dir.1.abs() > 1 {
+                // 두 칸 가는 홀소리는 화살촉이 둘
+                format!(
+                    " {},{}",
+                    cx + ux * (start + end) / 2,
+                    cy + uy * (start + end) / 2
+                )
+            } else {
+                String::new()
+            };
+            format!(
+                "<polyline points=\"{},{}{} {},{}\" class=\"arrow\"/>",
+                cx + ux * start,
+                cy + uy * start,
+                mid,
+                cx + ux * end,
+                cy + uy * end
+            )
+        }
+    }
+}
+
+/// 지난 셀과 그 셀로 들어온 방향
+pub type Step = ((i32, i32), (i32, i32));
+
+/// 실행 전 상태의 `aheui`를 `input`으로 최대 `steps` 걸음 실행하며 지난 셀.
+/// 빈 격자는 지나는 셀이 없다
+pub fn trace(aheui: &Aheui, input: &str, steps: usize) -> Vec<Step> {
+    if aheui.src_mat.0 == 0 || aheui.src_mat.1 == 0 {
+        return vec![];
+    }
+
+    let mut runner = aheui.clone();
+    runner.debug(true);
+    runner.set_input(input.to_string());
+
+    let mut path = vec![(runner.cursor, runner.dir)];
+    while !runner.ended && runner.step < steps {
+        runner.next();
+        if !runner.ended {
+            path.push((runner.cursor, runner.dir));
+        }
+    }
+    path
+}
+
+/// 격자를 명령 묶음별 색과 방향 화살표로 그린다. `path`가 있으면 그 위에 실행 경로를 겹친다
+pub fn render(aheui: &Aheui, path: Option<&[Step]>) -> String {
+    let (w, h) = (aheui.src_mat.0 as i32, aheui.src_mat.1 as i32);
+    let legend = CELL / 2;
+    let (width, height) = (
+        (w * CELL).max(LEGEND * FAMILIES.len() as i32),
+        h * CELL + legend * 2,
+    );
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        width, height, width, height
+    );
+    out += "<defs>\n";
+    out += "  <marker id=\"head\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"4\" markerHeight=\"4\" orient=\"auto\">\n";
+    out += "    <path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"#333\"/>\n";
+    out += "  </marker>\n";
+    out += "  <style>\n";
+    out += "    .cell { stroke: #999; stroke-width: 1; }\n";
+    out += "    .chr { font-family: sans-serif; font-size: 20px; text-anchor: middle; dominant-baseline: central; }\n";
+    out += "    .arrow { fill: none; stroke: #333; stroke-width: 1.5; marker-mid: url(#head); marker-end: url(#head); }\n";
+    out += "    .bar { stroke: #333; stroke-width: 3; }\n";
+    out += "    .path { fill: none; stroke: #d00; stroke-width: 3; stroke-opacity: 0.6; stroke-linejoin: round; }\n";
+    out +=
+        "    .legend { font-family: sans-serif; font-size: 11px; dominant-baseline: central; }\n";
+    out += "  </style>\n";
+    out += "</defs>\n";
+
+    for (y, row) in aheui.src_map.iter().enumerate() {
+        for (x, &chr) in row.iter().enumerate() {
+            let (px, py) = (x as i32 * CELL, y as i32 * CELL);
+            let (cx, cy) = (px + CELL / 2, py + CELL / 2);
+            out += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" class=\"cell\"/>\n",
+                px,
+                py,
+                CELL,
+                CELL,
+                FAMILIES[family(chr)].1
+            );
+            out += &format!(
+                "<text x=\"{}\" y=\"{}\" class=\"chr\">{}</text>\n",
+                cx,
+                cy,
+                escape(chr.3)
+            );
+            let arrow = arrow(chr, cx, cy);
+            if !arrow.is_empty() {
+                out += &arrow;
+                out += "\n";
+            }
+        }
+    }
+
+    if let Some(path) = path {
+        // 가장자리를 넘어 감싸는 이동에서 선을 끊는다
+        let center = |(x, y): (i32, i32)| (x * CELL + CELL / 2, y * CELL + CELL / 2);
+        let mut segments: Vec<Vec<(i32, i32)>> = vec![];
+        for (i, &(p, dir)) in path.iter().enumerate() {
+            let wraps = i > 0 && {
+                let q = path[i - 1].0;
+                p != (q.0 + dir.0, q.1 + dir.1)
+            };
+            if i == 0 || wraps {
+                segments.push(vec![]);
+            }
+            segments.last_mut().unwrap().push(center(p));
+        }
+        for segment in segments {
+            let points: Vec<String> = segment
+                .iter()
+                .map(|(x, y)| format!("{},{}", x, y))
+                .collect();
+            out += &format!(
+                "<polyline points=\"{}\" class=\"path\"/>\n",
+                points.join(" ")
+            );
+        }
+        if let Some(&(start, _)) = path.first() {
+            let (x, y) = center(start);
+            out += &format!(
+                "<circle cx=\"{}\" cy=\"{}\" r=\"5\" fill=\"#d00\"/>\n",
+                x, y
+            );
+        }
+    }
+
+    // 범례
+    let ly = h * CELL + legend;
+    for (i, (name, color)) in FAMILIES.iter().enumerate() {
+        let lx = i as i32 * LEGEND + 4;
+        out += &format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"10\" height=\"10\" fill=\"{}\" class=\"cell\"/>\n",
+            lx,
+            ly - 5,
+            color
+        );
+        out += &format!(
+            "<text x=\"{}\" y=\"{}\" class=\"legend\">{}</text>\n",
+            lx + 14,
+            ly,
+            name
+        );
+    }
+
+    out += "</svg>\n";
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(src: &str, steps: usize) -> usize {
+        let aheui = Aheui::new(src.to_string());
+        let path = trace(&aheui, "", steps);
+        render(&aheui, Some(&path))
+            .matches("class=\"path\"")
+            .count()
+    }
+
+    #[test]
+    fn breaks_the_path_at_wraps_on_narrow_grids() {
+        // (0, 0) (1, 0) | (0, 0) (1, 0) | (0, 0)
+        assert_eq!(segments("아아\n", 4), 3);
+    }
+
+    #[test]
+    fn keeps_fast_moves_in_one_segment() {
+        // (0, 0) (2, 0) (4, 0) | (1, 0) (3, 0)
+        assert_eq!(segments("야ㅇ야ㅇ야\n", 4), 2);
+    }
+
+    #[test]
+    fn traces_nothing_on_an_empty_grid() {
+        for src in ["", "\n\n"] {
+            assert_eq!(trace(&Aheui::new(src.to_string()), "", 10), vec![]);
+            assert_eq!(segments(src, 10), 0);
+        }
+    }
+
+    /// `class`가 붙은 빈 요소들
+    fn elements<'a>(svg: &'a str, class: &str) -> Vec<&'a str> {
+        let class = format!("class=\"{}\"", class);
+        svg.split_inclusive("/>")
+            .map(|chunk| &chunk[chunk.rfind('<').unwrap()..])
+            .filter(|element| element.contains(&class))
+            .collect()
+    }
+
+    /// `class`가 붙은 요소들의 `attr` 값
+    fn attrs<'a>(svg: &'a str, class: &str, attr: &str) -> Vec<&'a str> {
+        let attr = format!(" {}=\"", attr);
+        elements(svg, class)
+            .into_iter()
+            .map(|element| {
+                let start = element.find(&attr).unwrap() + attr.len();
+                let len = element[start..].find('"').unwrap();
+                &element[start..start + len]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fills_each_cell_with_its_family() {
+        let svg = render(&Aheui::new("다뱌서오카x\n으희\n".to_string()), None);
+        let fills = attrs(&svg, "cell", "fill");
+        // 격자 12칸 뒤에 범례 6칸
+        assert_eq!(
+            fills[..12],
+            [
+                FAMILIES[0].1,
+                FAMILIES[1].1,
+                FAMILIES[2].1,
+                FAMILIES[3].1,
+                FAMILIES[4].1,
+                FAMILIES[5].1,
+                FAMILIES[3].1,
+                FAMILIES[3].1,
+                FAMILIES[5].1,
+                FAMILIES[5].1,
+                FAMILIES[5].1,
+                FAMILIES[5].1,
+            ]
+        );
+        assert_eq!(fills[12..], FAMILIES.map(|(_, color)| color));
+    }
+
+    #[test]
+    fn points_arrows_along_each_vowel() {
+        let svg = render(&Aheui::new("다뱌서오카x\n으희\n".to_string()), None);
+        assert_eq!(
+            attrs(&svg, "arrow", "points"),
+            [
+                // ㅏ 오른쪽
+                "32,20 39,20",
+                // ㅑ 오른쪽으로 두 칸, 화살촉이 둘
+                "72,20 75,20 79,20",
+                // ㅓ 왼쪽
+                "88,20 81,20",
+                // ㅗ 위쪽
+                "140,8 140,1",
+                // 카도 오른쪽
+                "192,20 199,20",
+            ]
+        );
+        // ㅡ는 가로 막대, ㅢ는 가로와 세로 막대
+        assert_eq!(
+            elements(&svg, "bar"),
+            [
+                "<line x1=\"7\" y1=\"60\" x2=\"33\" y2=\"60\" class=\"bar\"/>",
+                "<line x1=\"47\" y1=\"60\" x2=\"73\" y2=\"60\" class=\"bar\"/>",
+                "<line x1=\"60\" y1=\"47\" x2=\"60\" y2=\"73\" class=\"bar\"/>",
+            ]
+        );
+    }
+}
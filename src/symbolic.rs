@@ -0,0 +1,624 @@
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use crate::aheui::{Aheui, advance, turn};
+use crate::hangul::{count_lines_in_char, get_end_count};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Mul,
+    Sub,
+    Div,
+    Rem,
+}
+
+/// 입력에 따라 달라지는 값
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Const(i32),
+    /// 몇 번째 입력
+    Var(usize),
+    Bin(BinOp, Rc<Expr>, Rc<Expr>),
+}
+
+impl Expr {
+    fn bin(op: BinOp, lhs: Rc<Expr>, rhs: Rc<Expr>) -> Rc<Expr> {
+        match (&*lhs, &*rhs) {
+            (Expr::Const(a), Expr::Const(b)) => match apply(op, *a, *b) {
+                Some(v) => Rc::new(Expr::Const(v)),
+                None => Rc::new(Expr::Bin(op, lhs, rhs)),
+            },
+            _ => Rc::new(Expr::Bin(op, lhs, rhs)),
+        }
+    }
+
+    fn constant(&self) -> Option<i32> {
+        match self {
+            Expr::Const(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn eval(&self, vals: &[i32]) -> Option<i32> {
+        match self {
+            Expr::Const(v) => Some(*v),
+            Expr::Var(i) => vals.get(*i).copied(),
+            Expr::Bin(op, lhs, rhs) => apply(*op, lhs.eval(vals)?, rhs.eval(vals)?),
+        }
+    }
+
+    fn max_var(&self) -> Option<usize> {
+        match self {
+            Expr::Const(_) => None,
+            Expr::Var(i) => Some(*i),
+            Expr::Bin(_, lhs, rhs) => lhs.max_var().max(rhs.max_var()),
+        }
+    }
+
+    fn vars(&self, out: &mut Vec<usize>) {
+        match self {
+            Expr::Const(_) => {}
+            Expr::Var(i) => out.push(*i),
+            Expr::Bin(_, lhs, rhs) => {
+                lhs.vars(out);
+                rhs.vars(out);
+            }
+        }
+    }
+}
+
+fn apply(op: BinOp, a: i32, b: i32) -> Option<i32> {
+    match op {
+        BinOp::Add => Some(a.wrapping_add(b)),
+        BinOp::Mul => Some(a.wrapping_mul(b)),
+        BinOp::Sub => Some(a.wrapping_sub(b)),
+        BinOp::Div => (b != 0).then(|| a.wrapping_div(b)),
+        BinOp::Rem => (b != 0).then(|| a.wrapping_rem(b)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rel {
+    Eq,
+    Ne,
+    Ge,
+    Lt,
+}
+
+/// `ㅈ`, `ㅊ` 갈림길에서 모은 조건
+#[derive(Debug, Clone)]
+pub struct Constraint {
+    pub lhs: Rc<Expr>,
+    pub rel: Rel,
+    pub rhs: Rc<Expr>,
+}
+
+impl Constraint {
+    pub fn holds(&self, vals: &[i32]) -> bool {
+        let (Some(a), Some(b)) = (self.lhs.eval(vals), self.rhs.eval(vals)) else {
+            return false;
+        };
+
+        match self.rel {
+            Rel::Eq => a == b,
+            Rel::Ne => a != b,
+            Rel::Ge => a >= b,
+            Rel::Lt => a < b,
+        }
+    }
+
+    fn max_var(&self) -> Option<usize> {
+        self.lhs.max_var().max(self.rhs.max_var())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    /// `ㅂㅇ`, 숫자 한 줄
+    Number,
+    /// `ㅂㅎ`, 글자 한 개와 줄바꿈
+    Char,
+}
+
+/// 입력값 후보를 만들어 차례로 대입하는 작은 정수 풀이기
+pub struct Solver<'a> {
+    kinds: &'a [InputKind],
+    constraints: &'a [Constraint],
+    /// 변수 i까지 정해지면 검사할 수 있는 조건
+    ready: Vec<Vec<usize>>,
+    candidates: Vec<Vec<i32>>,
+    budget: usize,
+}
+
+impl<'a> Solver<'a> {
+    pub fn new(kinds: &'a [InputKind], constraints: &'a [Constraint], budget: usize) -> Self {
+        let mut ready = vec![Vec::new(); kinds.len()];
+        let mut always = true;
+        for (idx, c) in constraints.iter().enumerate() {
+            match c.max_var() {
+                Some(v) => ready[v].push(idx),
+                None => always &= c.holds(&[]),
+            }
+        }
+
+        let candidates = (0..kinds.len())
+            .map(|v| candidates(v, kinds[v], constraints))
+            .collect();
+
+        Self {
+            kinds,
+            constraints,
+            ready,
+            candidates,
+            budget: if always { budget } else { 0 },
+        }
+    }
+
+    pub fn solve(mut self) -> Option<Vec<i32>> {
+        if self.budget == 0 {
+            return None;
+        }
+
+        let mut vals = Vec::with_capacity(self.kinds.len());
+        if self.search(&mut vals) {
+            Some(vals)
+        } else {
+            None
+        }
+    }
+
+    fn search(&mut self, vals: &mut Vec<i32>) -> bool {
+        let var = vals.len();
+        if var == self.kinds.len() {
+            return true;
+        }
+
+        for idx in 0..self.candidates[var].len() {
+            if self.budget == 0 {
+                return false;
+            }
+            self.budget -= 1;
+
+            vals.push(self.candidates[var][idx]);
+            if self.ready[var]
+                .iter()
+                .all(|&c| self.constraints[c].holds(vals))
+                && self.search(vals)
+            {
+                return true;
+            }
+            vals.pop();
+        }
+
+        false
+    }
+}
+
+fn valid_input(kind: InputKind, val: i32) -> bool {
+    match kind {
+        InputKind::Number => true,
+        InputKind::Char => char::from_u32(val as u32).is_some_and(|c| !c.is_control()),
+    }
+}
+
+/// 변수 하나에 대해 선형인 조건에서는 경계값을, 나머지는 작은 값들을 후보로 쓴다
+fn candidates(var: usize, kind: InputKind, constraints: &[Constraint]) -> Vec<i32> {
+    let mut vals = Vec::new();
+
+    for c in constraints {
+        let mut vars = Vec::new();
+        c.lhs.vars(&mut vars);
+        c.rhs.vars(&mut vars);
+        if vars.is_empty() || vars.iter().any(|&v| v != var) {
+            continue;
+        }
+
+        let f = |x: i32| {
+            let mut env = vec![0; var + 1];
+            env[var] = x;
+            Some(c.lhs.eval(&env)? as i64 - c.rhs.eval(&env)? as i64)
+        };
+        let (Some(f0), Some(f1), Some(f2)) = (f(0), f(1), f(2)) else {
+            continue;
+        };
+        let slope = f1 - f0;
+        if slope != 0 && f2 - f1 == slope {
+            let root = -f0 / slope;
+            for x in root - 1..=root + 1 {
+                if let Ok(x) = i32::try_from(x) {
+                    vals.push(x);
+                }
+            }
+        }
+    }
+
+    match kind {
+        InputKind::Number => vals.extend((0..=16).flat_map(|n| [n, -n])),
+        InputKind::Char => vals.extend((b'a'..=b'z').chain(b'0'..=b'9').map(i32::from)),
+    }
+
+    let mut out = Vec::new();
+    for v in vals {
+        if valid_input(kind, v) && !out.contains(&v) {
+            out.push(v);
+        }
+    }
+
+    out
+}
+
+/// 탐색 한도
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// 한 경로의 최대 걸음
+    pub steps: usize,
+    /// 전체 걸음 수의 합
+    pub total: usize,
+    /// 풀이기가 시도할 대입 수
+    pub solver: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            steps: 10_000,
+            total: 1_000_000,
+            solver: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Path {
+    cursor: (i32, i32),
+    dir: (i32, i32),
+    sel: usize,
+    stacks: [VecDeque<Rc<Expr>>; 27],
+    inputs: Vec<InputKind>,
+    constraints: Vec<Constraint>,
+    steps: usize,
+}
+
+impl Path {
+    fn pop(&mut self) -> Rc<Expr> {
+        self.stacks[self.sel]
+            .pop_front()
+            .unwrap_or(Rc::new(Expr::Const(0)))
+    }
+
+    fn insert(&mut self, sel: usize, val: Rc<Expr>) {
+        if sel == 21 {
+            self.stacks[21].push_back(val);
+        } else {
+            self.stacks[sel].push_front(val);
+        }
+    }
+
+    fn push_front(&mut self, val: Rc<Expr>) {
+        self.stacks[self.sel].push_front(val);
+    }
+
+    fn constrain(&mut self, lhs: Rc<Expr>, rel: Rel, rhs: Rc<Expr>) {
+        self.constraints.push(Constraint { lhs, rel, rhs });
+    }
+
+    fn input(&mut self, kind: InputKind) -> Rc<Expr> {
+        self.inputs.push(kind);
+        Rc::new(Expr::Var(self.inputs.len() - 1))
+    }
+
+    fn solve(&self, budget: usize) -> Option<Vec<i32>> {
+        Solver::new(&self.inputs, &self.constraints, budget).solve()
+    }
+
+    /// 이동. `valid`가 아니면 반사
+    fn moved(mut self, aheui: &Aheui, vowel: char, valid: bool) -> Self {
+        self.dir = turn(vowel, self.dir);
+        if !valid {
+            self.dir = (-self.dir.0, -self.dir.1);
+        }
+        self.cursor = advance(self.cursor, self.dir, aheui.src_mat);
+        self
+    }
+}
+
+/// 풀이한 값을 입력 파일 내용으로
+pub fn render_input(kinds: &[InputKind], vals: &[i32]) -> String {
+    kinds
+        .iter()
+        .zip(vals)
+        .map(|(kind, &val)| match kind {
+            InputKind::Number => format!("{}\n", val),
+            InputKind::Char => format!("{}\n", char::from_u32(val as u32).unwrap_or(' ')),
+        })
+        .collect()
+}
+
+/// `target` 셀에 도달하는 입력을 찾는다. 찾은 입력은 인터프리터로 다시 확인한다.
+/// `aheui`는 실행 전 상태여야 한다
+pub fn find_input(aheui: &Aheui, target: (i32, i32), limits: Limits) -> Option<String> {
+    if aheui.src_map.is_empty() || aheui.src_mat.0 == 0 {
+        return None;
+    }
+
+    let mut paths = VecDeque::from([Path {
+        cursor: (0, 0),
+        dir: (0, 1),
+        sel: 0,
+        stacks: Default::default(),
+        inputs: Vec::new(),
+        constraints: Vec::new(),
+        steps: 0,
+    }]);
+    let mut total = 0;
+
+    while let Some(path) = paths.pop_front() {
+        if path.cursor == target {
+            if let Some(vals) = path.solve(limits.solver) {
+                let input = render_input(&path.inputs, &vals);
+                if reaches(aheui, target, &input, limits.steps) {
+                    return Some(input);
+                }
+            }
+            continue;
+        }
+
+        if path.steps >= limits.steps || total >= limits.total {
+            continue;
+        }
+        total += 1;
+
+        let known = path.constraints.len();
+        for next in step(aheui, path) {
+            // 새 조건이 생긴 경로만 풀어 본다
+            if next.constraints.len() == known || next.solve(limits.solver).is_some() {
+                paths.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+fn step(aheui: &Aheui, mut path: Path) -> Vec<Path> {
+    let curr = aheui.src_map[path.cursor.1 as usize][path.cursor.0 as usize];
+    path.steps += 1;
+
+    if curr.0 == ' ' {
+        path.cursor = advance(path.cursor, path.dir, aheui.src_mat);
+        return vec![path];
+    }
+
+    let need = match curr.0 {
+        'ㄷ' | 'ㄸ' | 'ㅌ' | 'ㄴ' | 'ㄹ' | 'ㅍ' | 'ㅈ' => 2,
+        'ㅁ' | 'ㅃ' | 'ㅆ' | 'ㅊ' => 1,
+        _ => 0,
+    };
+    if path.stacks[path.sel].len() < need {
+        return vec![path.moved(aheui, curr.1, false)];
+    }
+
+    let zero = || Rc::new(Expr::Const(0));
+    let mut valid = true;
+
+    match curr.0 {
+        'ㅇ' | 'ㄱ' | 'ㄲ' => {}
+        'ㅎ' => return Vec::new(),
+        'ㄷ' | 'ㄸ' | 'ㅌ' | 'ㄴ' | 'ㄹ' => {
+            let num1 = path.pop();
+            let num2 = path.pop();
+            let op = match curr.0 {
+                'ㄷ' => BinOp::Add,
+                'ㄸ' => BinOp::Mul,
+                'ㅌ' => BinOp::Sub,
+                'ㄴ' => BinOp::Div,
+                _ => BinOp::Rem,
+            };
+
+            if matches!(op, BinOp::Div | BinOp::Rem) {
+                // 나누는 수(맨 위 값)가 0이면 끝나므로 이어지는 경로만 따라간다
+                match num1.constant() {
+                    Some(0) => return Vec::new(),
+                    Some(_) => {}
+                    None => path.constrain(num1.clone(), Rel::Ne, zero()),
+                }
+            }
+
+            let val = match op {
+                BinOp::Add | BinOp::Mul => Expr::bin(op, num1, num2),
+                _ => Expr::bin(op, num2, num1),
+            };
+            path.push_front(val);
+        }
+        'ㅁ' => {
+            path.pop();
+        }
+        'ㅂ' => {
+            let sel = path.sel;
+            match curr.2 {
+                ('ㅇ', ' ') => {
+                    let val = path.input(InputKind::Number);
+                    path.insert(sel, val);
+                }
+                ('ㅎ', ' ') => {
+                    // 한 줄을 통째로 넣으므로 글자 뒤에 줄바꿈이 따라온다
+                    let val = path.input(InputKind::Char);
+                    path.insert(sel, val);
+                    path.insert(sel, Rc::new(Expr::Const('\n' as i32)));
+                }
+                c => path.insert(sel, Rc::new(Expr::Const(count_lines_in_char(c)))),
+            }
+        }
+        'ㅃ' => {
+            let num = path.pop();
+            path.push_front(num.clone());
+            path.push_front(num);
+        }
+        'ㅍ' => {
+            let num1 = path.pop();
+            let num2 = path.pop();
+            path.push_front(num1);
+            path.push_front(num2);
+        }
+        // ㅎ 저장공간(통로)은 인터프리터에 없어 더 진행하지 못한다
+        'ㅅ' | 'ㅆ' if get_end_count(curr.2) >= path.stacks.len() => return Vec::new(),
+        'ㅅ' => path.sel = get_end_count(curr.2),
+        'ㅆ' => {
+            let num = path.pop();
+            path.insert(get_end_count(curr.2), num);
+        }
+        'ㅈ' => {
+            let num1 = path.pop();
+            let num2 = path.pop();
+            let sel = path.sel;
+
+            if let (Some(a), Some(b)) = (num1.constant(), num2.constant()) {
+                path.insert(sel, Rc::new(Expr::Const((b >= a) as i32)));
+            } else {
+                let mut less = path.clone();
+                less.constrain(num2.clone(), Rel::Lt, num1.clone());
+                less.insert(sel, zero());
+                path.constrain(num2, Rel::Ge, num1);
+                path.insert(sel, Rc::new(Expr::Const(1)));
+
+                return vec![
+                    path.moved(aheui, curr.1, true),
+                    less.moved(aheui, curr.1, true),
+                ];
+            }
+        }
+        'ㅊ' => {
+            let num = path.pop();
+
+            if let Some(v) = num.constant() {
+                valid = v != 0;
+            } else {
+                let mut zeroed = path.clone();
+                zeroed.constrain(num.clone(), Rel::Eq, zero());
+                path.constrain(num, Rel::Ne, zero());
+
+                return vec![
+                    path.moved(aheui, curr.1, true),
+                    zeroed.moved(aheui, curr.1, false),
+                ];
+            }
+        }
+        _ => valid = false,
+    }
+
+    vec![path.moved(aheui, curr.1, valid)]
+}
+
+/// 실제 인터프리터로 `input`을 넣어 `target`에 도달하는지 확인. `aheui`는 실행 전 상태
+pub fn reaches(aheui: &Aheui, target: (i32, i32), input: &str, steps: usize) -> bool {
+    let mut runner = aheui.clone();
+    runner.debug(true);
+    runner.set_input(input.to_string());
+
+    for _ in 0..steps {
+        if runner.cursor == target {
+            return true;
+        }
+        if runner.ended {
+            return false;
+        }
+        runner.next();
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(src: &str, target: (i32, i32)) -> Option<String> {
+        find_input(&Aheui::new(src.to_string()), target, Limits::default())
+    }
+
+    #[test]
+    fn divides_a_zero_dividend() {
+        // 0 / 2와 0 % 2는 끝나지 않고 이어진다
+        assert_eq!(find("바반나하", (3, 0)), Some(String::new()));
+        assert_eq!(find("바반라하", (3, 0)), Some(String::new()));
+    }
+
+    #[test]
+    fn leaves_the_dividend_unconstrained() {
+        // 읽은 수를 2로 나누므로 0을 읽어도 된다
+        let aheui = Aheui::new("방반나하".to_string());
+        assert!(reaches(&aheui, (3, 0), "0\n", 100));
+        assert_eq!(find("방반나하", (3, 0)).as_deref(), Some("0\n"));
+        assert!(find("밯반나하", (3, 0)).is_some());
+    }
+
+    #[test]
+    fn needs_a_non_zero_divisor() {
+        let input = find("반방나하", (3, 0)).expect("reachable");
+        assert_ne!(input, "0\n");
+        assert_eq!(find("반바나하", (3, 0)), None);
+    }
+
+    #[test]
+    fn wraps_like_the_interpreter() {
+        assert_eq!(apply(BinOp::Div, i32::MIN, -1), Some(i32::MIN));
+        assert_eq!(apply(BinOp::Rem, i32::MIN, -1), Some(0));
+        assert_eq!(apply(BinOp::Div, 7, 0), None);
+    }
+
+    /// 찾은 입력이 실제로 `target`에 닿는지도 본다
+    fn find_reaching(src: &str, target: (i32, i32)) -> String {
+        let input = find(src, target).expect("reachable");
+        assert!(reaches(&Aheui::new(src.to_string()), target, &input, 1000));
+        input
+    }
+
+    #[test]
+    fn takes_either_side_of_a_zero_branch() {
+        // 0이 아니면 둘째 행, 0이면 위로 감싸 셋째 행
+        let src = "방추\nㅇ반망희\n번어히멍\n";
+        assert_eq!(find_reaching(src, (3, 2)), "0\n");
+        assert_ne!(find_reaching(src, (3, 1)), "0\n");
+    }
+
+    #[test]
+    fn solves_a_comparison() {
+        // 읽은 수가 2 이상이면 둘째 행, 아니면 셋째 행
+        let src = "방박자추\nㅇㅇㅇ희\nㅇㅇㅇ히\n";
+        let parse = |input: String| input.trim_end().parse::<i32>().unwrap();
+        assert!(parse(find_reaching(src, (3, 1))) >= 2);
+        assert!(parse(find_reaching(src, (3, 2))) < 2);
+    }
+
+    #[test]
+    fn solves_a_character() {
+        // 글자 위의 줄바꿈을 버리고 9 * 9를 빼 0이면 셋째 행
+        let src = "밯마밟밟따타추\nㅇㅇㅇㅇㅇㅇ희\nㅇㅇㅇㅇㅇㅇ히\n";
+        assert_eq!(find_reaching(src, (6, 2)), "Q\n");
+        assert_ne!(find_reaching(src, (6, 1)), "Q\n");
+    }
+
+    #[test]
+    fn gives_up_on_unreachable_targets() {
+        // 실행되지 않는 행
+        assert_eq!(find("반망희\nㅇㅇㅇ\n", (0, 1)), None);
+        // 쌓은 2는 0이 될 수 없다
+        assert_eq!(find("박추\nㅇ희\nㅇ히\n", (1, 2)), None);
+        // 닿기 전에 걸음 한도가 끝난다
+        let aheui = Aheui::new("방반반반반추\nㅇㅇㅇㅇㅇ희\n".to_string());
+        let limits = Limits {
+            steps: 3,
+            ..Limits::default()
+        };
+        assert_eq!(find_input(&aheui, (5, 1), limits), None);
+        assert!(find_input(&aheui, (5, 1), Limits::default()).is_some());
+    }
+
+    #[test]
+    fn stops_at_the_missing_storage() {
+        // ㅎ 저장공간을 고르거나 옮기는 칸 뒤로는 가지 않는다
+        assert_eq!(find("샇희", (1, 0)), None);
+        assert_eq!(find("반쌓희", (2, 0)), None);
+    }
+}
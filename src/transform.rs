@@ -0,0 +1,277 @@
+use std::str::FromStr;
+
+use crate::aheui::{Aheui, advance};
+use crate::analysis::DepthAnalysis;
+use crate::hangul::{FILLER, assemble_jamo};
+
+/// 프로그램 격자를 돌리거나 뒤집는 방법
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// 시계 방향 90도
+    Rotate90,
+    Rotate180,
+    /// 시계 방향 270도
+    Rotate270,
+    /// 왼쪽 위에서 오른쪽 아래로 가는 대각선으로 뒤집기
+    Transpose,
+    /// 좌우 뒤집기
+    MirrorH,
+    /// 상하 뒤집기
+    MirrorV,
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rotate90" | "90" => Ok(Self::Rotate90),
+            "rotate180" | "180" => Ok(Self::Rotate180),
+            "rotate270" | "270" => Ok(Self::Rotate270),
+            "transpose" => Ok(Self::Transpose),
+            "mirror-h" => Ok(Self::MirrorH),
+            "mirror-v" => Ok(Self::MirrorV),
+            _ => Err(format!(
+                "unknown transform {:?}, expected rotate90, rotate180, rotate270, transpose, mirror-h or mirror-v",
+                s
+            )),
+        }
+    }
+}
+
+impl Transform {
+    /// 가로와 세로가 바뀌는지
+    fn swaps_axes(self) -> bool {
+        matches!(self, Self::Rotate90 | Self::Rotate270 | Self::Transpose)
+    }
+
+    pub fn size(self, (w, h): (usize, usize)) -> (usize, usize) {
+        if self.swaps_axes() { (h, w) } else { (w, h) }
+    }
+
+    pub fn pos(self, (x, y): (i32, i32), (w, h): (usize, usize)) -> (i32, i32) {
+        let (w, h) = (w as i32, h as i32);
+        match self {
+            Self::Rotate90 => (h - 1 - y, x),
+            Self::Rotate180 => (w - 1 - x, h - 1 - y),
+            Self::Rotate270 => (y, w - 1 - x),
+            Self::Transpose => (y, x),
+            Self::MirrorH => (w - 1 - x, y),
+            Self::MirrorV => (x, h - 1 - y),
+        }
+    }
+
+    pub fn dir(self, (dx, dy): (i32, i32)) -> (i32, i32) {
+        match self {
+            Self::Rotate90 => (-dy, dx),
+            Self::Rotate180 => (-dx, -dy),
+            Self::Rotate270 => (dy, -dx),
+            Self::Transpose => (dy, dx),
+            Self::MirrorH => (-dx, dy),
+            Self::MirrorV => (dx, -dy),
+        }
+    }
+
+    /// `turn`이 같은 방향으로 보내도록 바꾼 홀소리
+    pub fn vowel(self, vowel: char) -> char {
+        match vowel {
+            'ㅣ' | 'ㅡ' if self.swaps_axes() => {
+                if vowel == 'ㅣ' {
+                    'ㅡ'
+                } else {
+                    'ㅣ'
+                }
+            }
+            _ => match absolute(vowel) {
+                Some(dir) => VOWELS
+                    .iter()
+                    .find(|(_, d)| *d == self.dir(dir))
+                    .map_or(vowel, |(v, _)| *v),
+                None => vowel,
+            },
+        }
+    }
+}
+
+/// 들어온 방향과 상관없이 방향을 정하는 홀소리
+const VOWELS: [(char, (i32, i32)); 8] = [
+    ('ㅏ', (1, 0)),
+    ('ㅓ', (-1, 0)),
+    ('ㅗ', (0, -1)),
+    ('ㅜ', (0, 1)),
+    ('ㅑ', (2, 0)),
+    ('ㅕ', (-2, 0)),
+    ('ㅛ', (0, -2)),
+    ('ㅠ', (0, 2)),
+];
+
+fn absolute(vowel: char) -> Option<(i32, i32)> {
+    VOWELS.iter().find(|(v, _)| *v == vowel).map(|(_, d)| *d)
+}
+
+/// 격자를 바꾸고, 시작 칸과 처음 방향(아래)이 맞도록 필요하면 첫 행이나 첫 열에
+/// 진입로를 붙인다. 진입로 때문에 감싸는 크기가 달라져 도달 가능한 이동이
+/// 어긋나면 `None`
+pub fn transform(aheui: &Aheui, t: Transform) -> Option<String> {
+    let mat = aheui.src_mat;
+    if mat.0 == 0 || mat.1 == 0 {
+        return Some(String::new());
+    }
+
+    let (w, h) = t.size(mat);
+    let mut grid = vec![vec![FILLER; w]; h];
+    for (y, row) in aheui.src_map.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            let (nx, ny) = t.pos((x as i32, y as i32), mat);
+            grid[ny as usize][nx as usize] = if cell.0 == ' ' {
+                cell.3
+            } else {
+                assemble_jamo(cell.0, t.vowel(cell.1), cell.2)
+            };
+        }
+    }
+
+    let start = t.pos((0, 0), mat);
+    let down = t.dir((0, 1));
+    let first = aheui.src_map[0][0];
+    let needs_entry =
+        start != (0, 0) || (down != (0, 1) && (first.0 == ' ' || absolute(first.1).is_none()));
+
+    if !needs_entry {
+        return Some(render(&grid));
+    }
+
+    // 진입로 (처음 칸, 꺾는 칸)과 원래 격자가 옮겨진 거리
+    let (offset, entry) = if down.0 == 0 {
+        grid.insert(0, vec![FILLER; w]);
+        let turn = if down.1 > 0 { '우' } else { '오' };
+        let entry = if start.0 == 0 {
+            vec![((0, 0), turn)]
+        } else {
+            vec![((0, 0), '아'), ((start.0, 0), turn)]
+        };
+        ((0, 1), entry)
+    } else {
+        for row in grid.iter_mut() {
+            row.insert(0, FILLER);
+        }
+        let turn = if down.0 > 0 { '아' } else { '어' };
+        let entry = if start.1 == 0 {
+            vec![((0, 0), turn)]
+        } else {
+            vec![((0, 0), '우'), ((0, start.1), turn)]
+        };
+        ((1, 0), entry)
+    };
+    for &((x, y), chr) in &entry {
+        grid[y as usize][x as usize] = chr;
+    }
+
+    let new_mat = (grid[0].len(), grid.len());
+    let map = |p: (i32, i32)| {
+        let (x, y) = t.pos(p, mat);
+        (x + offset.0, y + offset.1)
+    };
+    let in_entry = |(x, y): (i32, i32)| (offset.0 == 1 && x == 0) || (offset.1 == 1 && y == 0);
+
+    // 원래 이동이 진입로의 빈칸만 지나 같은 칸에 닿는지
+    let analysis = DepthAnalysis::run(aheui);
+    let preserved = analysis.edges(aheui).iter().all(|(from, to)| {
+        let dir = t.dir(to.dir);
+        let mut pos = advance(map(from.cursor), dir, new_mat);
+        for _ in 0..2 {
+            if !in_entry(pos) || entry.iter().any(|&(p, _)| p == pos) {
+                break;
+            }
+            pos = advance(pos, dir, new_mat);
+        }
+        pos == map(to.cursor)
+    });
+
+    preserved.then(|| render(&grid))
+}
+
+fn render(grid: &[Vec<char>]) -> String {
+    grid.iter()
+        .map(|row| row.iter().collect::<String>() + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equiv::{SAMPLES, assert_equivalent};
+
+    const ALL: [Transform; 6] = [
+        Transform::Rotate90,
+        Transform::Rotate180,
+        Transform::Rotate270,
+        Transform::Transpose,
+        Transform::MirrorH,
+        Transform::MirrorV,
+    ];
+
+    fn apply(src: &str, t: Transform) -> Option<String> {
+        transform(&Aheui::new(src.to_string()), t)
+    }
+
+    #[test]
+    fn keeps_the_behavior() {
+        for src in SAMPLES {
+            for t in ALL {
+                if let Some(out) = apply(src, t) {
+                    assert_equivalent(src, &out);
+                }
+            }
+        }
+        // `border`만 감싸는 크기에 기대므로 전치만 된다
+        for (idx, src) in SAMPLES.iter().enumerate().filter(|(idx, _)| *idx != 3) {
+            assert!(ALL.iter().all(|&t| apply(src, t).is_some()), "{}", idx);
+        }
+    }
+
+    #[test]
+    fn adds_an_entry_when_the_start_moves() {
+        // 시작 칸이 왼쪽 아래로 가고 처음 방향이 오른쪽이 된다
+        let out = apply("방망희", Transform::Rotate270).unwrap();
+        assert_eq!(out, "우희\nㅇ몽\n아봉\n");
+        assert_equivalent("방망희", &out);
+        // 첫 칸이 방향을 정하면 진입로가 없다
+        assert_eq!(
+            apply("방망희", Transform::Rotate90).unwrap(),
+            "붕\n뭉\n희\n"
+        );
+    }
+
+    #[test]
+    fn refuses_when_the_entry_shifts_a_wrap() {
+        // ㅠ가 세 줄 높이로 감싸 돌아오는데, 진입로 행이 붙으면 높이가 넷이 된다
+        let src = "반뷰\n망\n희\n";
+        assert_eq!(apply(src, Transform::MirrorV), None);
+        assert!(apply(src, Transform::Transpose).is_some());
+    }
+
+    #[test]
+    fn swaps_the_reflecting_vowels_with_the_axes() {
+        for t in [
+            Transform::Rotate90,
+            Transform::Rotate270,
+            Transform::Transpose,
+        ] {
+            assert_eq!(t.vowel('ㅣ'), 'ㅡ');
+            assert_eq!(t.vowel('ㅡ'), 'ㅣ');
+            assert_eq!(t.vowel('ㅢ'), 'ㅢ');
+        }
+        for t in [Transform::Rotate180, Transform::MirrorH, Transform::MirrorV] {
+            assert_eq!(t.vowel('ㅣ'), 'ㅣ');
+            assert_eq!(t.vowel('ㅡ'), 'ㅡ');
+        }
+        // 세로로 지나가는 ㅣ는 가로로 돌린 뒤에도 지나가야 한다
+        let src = "우\n빈\n망\n희\n";
+        for t in ALL {
+            if let Some(out) = apply(src, t) {
+                assert_equivalent(src, &out);
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+; 정수를 십진수 문자열로 바꾼다. `print_str`이 출력하는 꼴
+; 아: x → 0 dn .. d1
+; 쓰는 저장공간: 압(부호와 남은 수)
+        move 압
+        push 0
+        sel 압
+        dup
+        push 0
+        cmp
+        brz neg
+        push 0
+        swap
+        jmp digits
+; -x는 i32::MIN에서 넘치므로 음수인 채로 나머지를 뒤집어 쓴다
+neg:    push 1
+        swap
+nmore:  dup
+        push 10
+        mod
+        push 48
+        swap
+        sub
+        move 아
+        push 10
+        div
+        dup
+        brz done
+        jmp nmore
+; 압: 음수인지 x
+digits: dup
+        brz zero
+more:   dup
+        push 10
+        mod
+        push 48
+        add
+        move 아
+        push 10
+        div
+        dup
+        brz done
+        jmp more
+zero:   push 48
+        move 아
+done:   pop
+        brz end
+        push 45
+        move 아
+end:    sel 아
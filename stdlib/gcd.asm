@@ -0,0 +1,20 @@
+; 음이 아닌 두 정수의 최대공약수. 유클리드 호제법
+; 아: a b → gcd(a, b)
+; 쓰는 저장공간: 앋(b)
+loop:   dup
+        brz done
+        swap
+        dup
+        brz azero
+        swap
+        dup
+        move 앋
+        mod
+        sel 앋
+        move 아
+        sel 아
+        swap
+        jmp loop
+; 0은 나눌 수 없으므로 gcd(0, b) = b
+azero:
+done:   pop
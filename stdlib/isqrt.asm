@@ -0,0 +1,32 @@
+; 음이 아닌 정수의 제곱근을 내림한다. 뉴턴 방법으로 n부터 줄여 나간다
+; 아: n → isqrt(n)
+; 쓰는 저장공간: 안(n)
+        dup
+        brz zero
+        dup
+        move 안
+; x <= n / x 이면 x가 답, 아니면 x -= (x - n / x + 1) / 2
+loop:   dup
+        dup
+        sel 안
+        dup
+        move 아
+        sel 아
+        swap
+        div
+        sub
+        dup
+        push 1
+        cmp
+        brz done
+        push 1
+        add
+        push 2
+        div
+        sub
+        jmp loop
+done:   pop
+        sel 안
+        pop
+        sel 아
+zero:
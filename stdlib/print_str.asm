@@ -0,0 +1,8 @@
+; 0으로 끝나는 문자열을 첫 글자부터 출력한다
+; 아: 0 cn .. c1 →
+; 쓰는 저장공간: 없음
+loop:   dup
+        brz done
+        print char
+        jmp loop
+done:   pop
@@ -0,0 +1,75 @@
+; 한 줄을 읽어 그 안의 정수를 차례로 `아`에 넣고 개수를 맨 위에 둔다.
+; 숫자가 아닌 글자는 가르개이고, 숫자 바로 앞의 `-`는 부호
+; 아: → v1 .. vn n
+; 쓰는 저장공간: 앙(줄), 악(읽는 수, 아직 없으면 -1), 앆(개수), 앇(부호)
+        sel 앙
+        input char
+        push 0
+        sel 앆
+        push 0
+        sel 앇
+        push 1
+        sel 악
+        push -1
+loop:   sel 앙
+        move 악
+        sel 악
+        dup
+        push 48
+        cmp
+        brz other
+        dup
+        push 57
+        swap
+        cmp
+        brz other
+        push 48
+        sub
+        swap
+        dup
+        push 0
+        cmp
+        brz start
+        push 10
+        mul
+        add
+        jmp loop
+start:  pop
+        jmp loop
+other:  swap
+        dup
+        push 0
+        cmp
+        brz skip
+        sel 앇
+        dup
+        move 악
+        sel 악
+        mul
+        move 아
+        sel 앆
+        push 1
+        add
+        sel 악
+        push -1
+skip:   swap
+        dup
+        brz end
+        push 45
+        sub
+        brz minus
+        sel 앇
+        pop
+        push 1
+        jmp loop
+minus:  sel 앇
+        pop
+        push -1
+        jmp loop
+end:    pop
+        pop
+        sel 앇
+        pop
+        sel 앆
+        move 아
+        sel 아
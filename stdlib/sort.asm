@@ -0,0 +1,73 @@
+; `read_ints`처럼 개수가 맨 위에 있는 값들을 정렬한다. 삽입 정렬
+; 아: v1 .. vn n → vmax .. vmin n
+; 쓰는 저장공간: 알(정렬된 값, 큰 것이 위), 앍(옮겨 둔 값), 앎(남은 개수),
+;                앏(정렬된 개수), 앐(옮긴 개수), 앑(넣을 값)
+        move 앎
+        sel 앏
+        push 0
+next:   sel 앎
+        dup
+        brz finish
+        push 1
+        sub
+        sel 아
+        move 앑
+        sel 앐
+        push 0
+; 옮긴 개수가 정렬된 개수와 같거나 위의 값이 넣을 값 이하이면 넣는다
+scan:   sel 앐
+        dup
+        sel 앏
+        dup
+        move 앐
+        sel 앐
+        sub
+        brz insert
+        sel 알
+        dup
+        move 아
+        sel 앑
+        dup
+        move 아
+        sel 아
+        swap
+        cmp
+        brz shift
+        jmp insert
+shift:  sel 알
+        move 앍
+        sel 앐
+        push 1
+        add
+        jmp scan
+insert: sel 앑
+        move 알
+back:   sel 앐
+        dup
+        brz grown
+        push 1
+        sub
+        sel 앍
+        move 알
+        jmp back
+grown:  pop
+        sel 앏
+        push 1
+        add
+        jmp next
+finish: pop
+        sel 앏
+        dup
+        move 앎
+out:    sel 앎
+        dup
+        brz done
+        push 1
+        sub
+        sel 알
+        move 아
+        jmp out
+done:   pop
+        sel 앏
+        move 아
+        sel 아
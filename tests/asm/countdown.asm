@@ -0,0 +1,12 @@
+; 10부터 1까지 센다
+        push 10
+loop:   dup
+        print num
+        push 10
+        print char
+        push 1
+        sub
+        dup
+        brz done
+        jmp loop
+done:   halt
@@ -0,0 +1,19 @@
+; 입력한 수의 계승
+        input num
+        sel ㄱ
+        push 1
+        sel 아
+loop:   dup
+        brz done
+        dup
+        move ㄱ
+        sel ㄱ
+        mul
+        sel 아
+        push 1
+        sub
+        jmp loop
+done:   sel ㄱ
+        print num
+        push 10
+        print char
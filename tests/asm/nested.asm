@@ -0,0 +1,36 @@
+; 3x3 곱셈표
+        push 1
+        sel ㄱ
+outer:  sel 아
+        dup
+        push 4
+        cmp
+        brz inner_start
+        halt
+inner_start:
+        push 1
+inner:  dup
+        push 4
+        cmp
+        brz body
+        pop
+        push 10
+        print char
+        push 1
+        add
+        jmp outer
+body:   dup
+        move ㄴ
+        swap
+        dup
+        move ㄴ
+        swap
+        sel ㄴ
+        mul
+        print num
+        push 32
+        print char
+        sel 아
+        push 1
+        add
+        jmp inner
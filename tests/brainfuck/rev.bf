@@ -0,0 +1,2 @@
+Reverse each line of input
+>,[>,]<[.<]